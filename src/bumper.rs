@@ -0,0 +1,719 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use figment::Provider;
+use thiserror::Error;
+
+use crate::changelog::ChangelogError;
+use crate::config::{
+    config_table, update_current_version, Config, ConfigError, PushConfig, PushRefs,
+    WORKDIR_CONFIG_PATH,
+};
+use crate::conventional::{AutoBump, BumpLevel};
+use crate::file_operations::{is_same_file, write_all, FileChange, FileOperationError};
+use crate::git_operations::{Git, GitOperationError, Push, Signing};
+use crate::replace::{prepare_file_changes, ReplaceError};
+use crate::{AnyVersion, Part, Placeholders};
+
+/// Errors of planning and applying a release
+#[derive(Debug, Error)]
+pub enum ReleaseError {
+    #[error(transparent)]
+    Version(#[from] crate::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Changelog(#[from] ChangelogError),
+    #[error("Unable to bump {} file(s), no files were written:\n  - {}", .0.len(), format_errors(.0))]
+    Files(Vec<ReplaceError>),
+    #[error(transparent)]
+    Write(#[from] FileOperationError),
+    #[error(transparent)]
+    Git(#[from] GitOperationError),
+    #[error("No commits {} call for a release", .since.as_ref().map_or("in the history".to_string(), |tag| format!("since {tag}")))]
+    NothingToRelease { since: Option<String> },
+}
+
+fn format_errors(errors: &[ReplaceError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("\n  - ")
+}
+
+/// How the new version is recorded in git
+#[derive(Debug, Clone, Default)]
+pub struct GitOptions {
+    pub commit: bool,
+    pub tag: bool,
+    /// Commit message, `{current_version}` and `{new_version}` are replaced
+    pub commit_message: String,
+    pub allow_dirty: bool,
+    /// Replace an existing tag instead of failing
+    pub force_tag: bool,
+    pub signing: Signing,
+    /// Push to a remote after committing and tagging, `None` to not push
+    pub push: Option<PushConfig>,
+}
+
+/// Bumps the version of the files described by a config
+#[derive(Debug, Clone)]
+pub struct Bumper {
+    config: Config,
+    config_path: PathBuf,
+    /// Git repository of the release, the working directory when `None`
+    repo_path: Option<PathBuf>,
+}
+
+impl Bumper {
+    pub fn new(config: Config, config_path: impl Into<PathBuf>) -> Self {
+        Bumper {
+            config,
+            config_path: config_path.into(),
+            repo_path: None,
+        }
+    }
+
+    /// Use the git repository at `path` instead of the working directory.
+    ///
+    /// Relative paths of the files and changelogs in the config are resolved against `path`,
+    /// the config path is used as given.
+    pub fn with_repo_path(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.config.files = self
+            .config
+            .files
+            .into_iter()
+            .map(|(file, config)| (path.join(file), config))
+            .collect();
+        if let Some(changelog) = &mut self.config.changelog {
+            changelog.path = path.join(&changelog.path);
+        }
+        if let Some(changelog) = &mut self.config.keep_a_changelog {
+            changelog.path = path.join(&changelog.path);
+        }
+        self.repo_path = Some(path);
+        self
+    }
+
+    /// Load the config file at `path`, or `./incrementor.toml` merged with `INCREMENTOR_`
+    /// environment variables when no path is given
+    ///
+    /// With `tag_source` enabled the current version is read from the git tags.
+    pub fn load(path: Option<&Path>) -> Result<Self, ReleaseError> {
+        match path {
+            Some(path) => Bumper::from_provider(Config::file(path), path),
+            None => Bumper::from_provider(Config::figment(), WORKDIR_CONFIG_PATH),
+        }
+    }
+
+    /// Extract the config from a provider, e.g. a config file merged with overrides.
+    ///
+    /// With `tag_source` enabled the current version is read from the git tags.
+    pub fn from_provider<T: Provider>(
+        provider: T,
+        config_path: impl Into<PathBuf>,
+    ) -> Result<Self, ReleaseError> {
+        let mut bumper = Bumper::new(Config::from(provider)?, config_path);
+        if bumper.config.tag_source.enabled {
+            bumper.use_tag_version()?;
+        }
+        Ok(bumper)
+    }
+
+    /// Use the highest version of the tags matching `tag_name` as the current version,
+    /// the configured `current_version` is kept when no tag matches
+    pub fn use_tag_version(&mut self) -> Result<(), ReleaseError> {
+        let git = self.git(true)?;
+        if let Some((_, tag)) = self.latest_tag(&git, self.config.tag_source.reachable)? {
+            if let Some(version) = self.config.tag_version(&tag) {
+                self.config.current_version = version.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// The tag matching `tag_name` with the highest version
+    fn latest_tag(
+        &self,
+        git: &Git,
+        reachable: bool,
+    ) -> Result<Option<(AnyVersion, String)>, ReleaseError> {
+        let mut highest: Option<(AnyVersion, String)> = None;
+        for tag in git.tags(reachable)? {
+            let Some(version) = self.config.tag_version(&tag) else {
+                continue;
+            };
+            // Tags that aren't valid versions of the scheme are ignored
+            let Ok(parsed) = self.config.scheme.parse(version) else {
+                continue;
+            };
+            if highest
+                .as_ref()
+                .is_none_or(|(current, _)| parsed > *current)
+            {
+                highest = Some((parsed, tag));
+            }
+        }
+        Ok(highest)
+    }
+
+    /// Derive the part to bump from the Conventional Commits since the latest version tag
+    /// reachable from HEAD, fails when none of the commits calls for a release
+    pub fn auto_bump(&self) -> Result<AutoBump, ReleaseError> {
+        let git = self.git(true)?;
+        let since = self.latest_tag(&git, true)?.map(|(_, tag)| tag);
+        let commits = git.commits_since(since.as_deref())?;
+        let initial_development = self
+            .current_version()?
+            .parts()
+            .iter()
+            .any(|(name, value)| name == "major" && value == "0");
+
+        let bump = AutoBump::from_commits(since, &commits, &self.config.auto, initial_development);
+        if bump.level == BumpLevel::None {
+            return Err(ReleaseError::NothingToRelease { since: bump.since });
+        }
+        Ok(bump)
+    }
+
+    /// Git of the repository of the release
+    fn git(&self, allow_dirty: bool) -> Result<Git, GitOperationError> {
+        match &self.repo_path {
+            Some(path) => Git::new_with_path(path, allow_dirty),
+            None => Git::new(allow_dirty),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// The current version parsed with the configured scheme
+    pub fn current_version(&self) -> Result<AnyVersion, crate::Error> {
+        self.config.version()
+    }
+
+    /// Parse a version with the configured scheme
+    pub fn parse_version(&self, version: &str) -> Result<AnyVersion, crate::Error> {
+        self.config.scheme.parse(version)
+    }
+
+    /// The next version when bumping `part` of the current version
+    pub fn next_version(
+        &self,
+        part: &Part,
+        build: Option<String>,
+        force: bool,
+    ) -> Result<AnyVersion, crate::Error> {
+        let options = self.config.bump_options(build, force);
+        self.config
+            .scheme
+            .bump(&self.current_version()?, part, &options)
+    }
+
+    /// Plan the release of `new_version` without writing anything.
+    ///
+    /// Fails when the git working directory is dirty and a commit or tag is requested,
+    /// or when one of the files does not contain the current version.
+    pub fn plan(
+        &self,
+        new_version: AnyVersion,
+        git: &GitOptions,
+    ) -> Result<ReleasePlan, ReleaseError> {
+        let current_version = self.current_version()?;
+
+        let repository = self.git(git.allow_dirty)?.with_signing(git.signing.clone());
+        if git.commit || git.tag {
+            repository.check_clean()?;
+        }
+
+        // The current version is searched for as written in the config
+        let placeholders = Placeholders {
+            current_version: &self.config.current_version,
+            new_version: &new_version,
+        };
+        let tag_name = placeholders.replace(&self.config.tag_name);
+
+        // An existing tag would only fail after the files are written
+        if git.tag && !git.force_tag {
+            if repository.tag_exists(&tag_name)? {
+                return Err(GitOperationError::TagExists {
+                    tag: tag_name,
+                    remote: None,
+                }
+                .into());
+            }
+            if self.config.check_remote_tags
+                && repository.remote_tag_exists(&self.config.push.remote, &tag_name)?
+            {
+                return Err(GitOperationError::TagExists {
+                    tag: tag_name,
+                    remote: Some(self.config.push.remote.clone()),
+                }
+                .into());
+            }
+        }
+
+        // Compute all replacements before writing anything
+        let mut changes = prepare_file_changes(&self.config, &placeholders)?;
+
+        // The version lives in the tags, the config is left as is
+        let config_path = (!self.config.tag_source.enabled).then(|| self.config_path.clone());
+
+        let today = Local::now().date_naive();
+
+        // Render the commits since the previous release into the changelog
+        if let Some(changelog) = &self.config.changelog {
+            let since = self.latest_tag(&repository, true)?.map(|(_, tag)| tag);
+            let commits = repository.commits_since(since.as_deref())?;
+            let section = changelog.render(&placeholders, today, &commits);
+            let change = pending_change(&mut changes, &changelog.path);
+            change.contents = changelog.insert(&change.contents, &section)?;
+        }
+
+        // Release the Unreleased section of a hand written changelog
+        if let Some(changelog) = &self.config.keep_a_changelog {
+            let change = pending_change(&mut changes, &changelog.path);
+            change.contents =
+                changelog.release(&change.contents, &new_version.to_string(), &tag_name, today)?;
+        }
+
+        // Finalize the config with the `new_version` as `current_version`, on top of the
+        // pending change when the config lives inside one of the bumped files
+        if let Some(config_path) = &config_path {
+            let change = pending_change(&mut changes, config_path);
+            change.contents = update_current_version(
+                &change.contents,
+                config_table(config_path),
+                &new_version.to_string(),
+            )?;
+        }
+
+        let commit_message = git
+            .commit
+            .then(|| placeholders.replace(&git.commit_message));
+        let tag_message = placeholders.replace(
+            self.config
+                .tag_message
+                .as_deref()
+                .unwrap_or(&self.config.tag_name),
+        );
+        let tag = git.tag.then_some(tag_name);
+        let tag_message = git.tag.then_some(tag_message);
+        // Without a tag there is only the branch to push, a replaced tag is force pushed
+        let force = if git.force_tag { "+" } else { "" };
+        let tag_ref = tag.as_ref().map(|tag| format!("{force}refs/tags/{tag}"));
+        let push = git
+            .push
+            .as_ref()
+            .map(|push| Push {
+                remote: push.remote.clone(),
+                refspecs: match push.refs {
+                    PushRefs::Branch => vec!["HEAD".to_string()],
+                    PushRefs::Tag => tag_ref.iter().cloned().collect(),
+                    PushRefs::Both => std::iter::once("HEAD".to_string())
+                        .chain(tag_ref.clone())
+                        .collect(),
+                },
+            })
+            .filter(|push| !push.refspecs.is_empty());
+
+        Ok(ReleasePlan {
+            current_version,
+            new_version,
+            changes,
+            config_path,
+            commit_message,
+            tag,
+            tag_message,
+            force_tag: git.force_tag,
+            push,
+            git: repository,
+        })
+    }
+}
+
+/// The pending change of the file at `path`, a new change of its current contents when the
+/// file isn't changed yet
+fn pending_change<'a>(changes: &'a mut Vec<FileChange>, path: &Path) -> &'a mut FileChange {
+    match changes
+        .iter()
+        .position(|change| is_same_file(&change.path, path))
+    {
+        Some(i) => &mut changes[i],
+        None => {
+            let original = fs::read_to_string(path).ok();
+            changes.push(FileChange {
+                path: path.to_path_buf(),
+                contents: original.clone().unwrap_or_default(),
+                original,
+                replacements: 0,
+            });
+            changes.last_mut().unwrap()
+        }
+    }
+}
+
+/// Everything a release changes, computed before anything is written
+#[derive(Debug, Clone)]
+pub struct ReleasePlan {
+    pub current_version: AnyVersion,
+    pub new_version: AnyVersion,
+    /// New contents of the configured files and the config
+    pub changes: Vec<FileChange>,
+    /// Config file that gets the new version as `current_version`, `None` when the
+    /// version is read from git tags
+    pub config_path: Option<PathBuf>,
+    /// Commit message, `None` when not committing
+    pub commit_message: Option<String>,
+    /// Tag name, `None` when not tagging
+    pub tag: Option<String>,
+    /// Tag annotation, `None` when not tagging
+    pub tag_message: Option<String>,
+    /// Replace the tag when it exists
+    pub force_tag: bool,
+    /// Refs pushed after committing and tagging, `None` when not pushing
+    pub push: Option<Push>,
+    git: Git,
+}
+
+impl ReleasePlan {
+    /// Write all files, then commit the changed ones, tag and push
+    pub fn apply(&self) -> Result<(), ReleaseError> {
+        write_all(&self.changes)?;
+
+        if let Some(message) = &self.commit_message {
+            // Only the files of the release are committed. Git resolves relative paths
+            // against the repository, the files are relative to the working directory.
+            let paths = self
+                .changes
+                .iter()
+                .filter(|change| change.original.as_deref() != Some(change.contents.as_str()))
+                .map(|change| std::path::absolute(&change.path))
+                .collect::<Result<Vec<PathBuf>, _>>()
+                .map_err(GitOperationError::from)?;
+            self.git.commit(message, &paths)?;
+        }
+
+        if let (Some(tag), Some(message)) = (&self.tag, &self.tag_message) {
+            self.git.tag(tag, message, self.force_tag)?;
+        }
+
+        if let Some(push) = &self.push {
+            self.git.push(push)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tempfile::tempdir;
+
+    use git2::{Oid, Repository, Signature};
+
+    use crate::config::{Config, FileConfig, PushConfig, PushRefs, TagSource};
+    use crate::git_operations::GitOperationError;
+    use crate::{Bumper, GitOptions, Part, ReleaseError, Scheme};
+
+    /// Commit to HEAD of the repository with an empty tree
+    fn commit(repo: &Repository, message: &str) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let dir = tempdir().unwrap();
+        let version = dir.path().join("VERSION");
+        let config_path = dir.path().join("incrementor.toml");
+        fs::write(&version, "0.1.0").unwrap();
+        fs::write(&config_path, "current_version = \"0.1.0\"\n").unwrap();
+
+        let mut config = Config {
+            current_version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        config.files.insert(version.clone(), FileConfig::default());
+        let bumper = Bumper::new(config, &config_path);
+
+        let new_version = bumper.next_version(&Part::Minor, None, false).unwrap();
+        let plan = bumper.plan(new_version, &GitOptions::default()).unwrap();
+        assert_eq!(plan.new_version.to_string(), "0.2.0");
+        assert_eq!(plan.changes.len(), 2);
+        assert_eq!(plan.commit_message, None);
+        assert_eq!(plan.tag, None);
+
+        // Planning doesn't write anything
+        assert_eq!(fs::read_to_string(&version).unwrap(), "0.1.0");
+
+        plan.apply().unwrap();
+        assert_eq!(fs::read_to_string(&version).unwrap(), "0.2.0");
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "current_version = \"0.2.0\"\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_tag() {
+        let dir = tempdir().unwrap();
+        Repository::init(dir.path()).unwrap();
+        let config_path = dir.path().join("incrementor.toml");
+        let git = GitOptions {
+            tag: true,
+            allow_dirty: true,
+            ..Default::default()
+        };
+        let new_version = Scheme::Semver.parse("1.3.0").unwrap();
+
+        let mut config = Config {
+            current_version: "1.2.0".to_string(),
+            ..Default::default()
+        };
+        let plan = Bumper::new(config.clone(), &config_path)
+            .with_repo_path(dir.path())
+            .plan(new_version.clone(), &git)
+            .unwrap();
+        assert_eq!(plan.tag.as_deref(), Some("v1.3.0"));
+        assert_eq!(plan.tag_message.as_deref(), Some("v1.3.0"));
+        assert_eq!(plan.push, None);
+
+        // Push the branch and the new tag, nothing when only the tag is pushed but not created
+        let push = GitOptions {
+            push: Some(PushConfig::default()),
+            ..git.clone()
+        };
+        let plan = Bumper::new(config.clone(), &config_path)
+            .with_repo_path(dir.path())
+            .plan(new_version.clone(), &push)
+            .unwrap();
+        assert_eq!(
+            plan.push.unwrap().to_string(),
+            "origin HEAD refs/tags/v1.3.0"
+        );
+        let push = GitOptions {
+            tag: false,
+            push: Some(PushConfig {
+                refs: PushRefs::Tag,
+                ..Default::default()
+            }),
+            ..git.clone()
+        };
+        let plan = Bumper::new(config.clone(), &config_path)
+            .with_repo_path(dir.path())
+            .plan(new_version.clone(), &push)
+            .unwrap();
+        assert_eq!(plan.push, None);
+
+        config.tag_name = "release/{new_version}".to_string();
+        config.tag_message = Some("Release {new_version}, after {current_version}".to_string());
+        let plan = Bumper::new(config, &config_path)
+            .with_repo_path(dir.path())
+            .plan(new_version, &git)
+            .unwrap();
+        assert_eq!(plan.tag.as_deref(), Some("release/1.3.0"));
+        assert_eq!(
+            plan.tag_message.as_deref(),
+            Some("Release 1.3.0, after 1.2.0")
+        );
+    }
+
+    #[test]
+    fn test_apply_tag() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let version = dir.path().join("VERSION");
+        let config_path = dir.path().join("incrementor.toml");
+        fs::write(&version, "0.1.0").unwrap();
+        fs::write(&config_path, "current_version = \"0.1.0\"\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        commit(&repo, "Initial commit");
+
+        let mut config = Config {
+            current_version: "0.1.0".to_string(),
+            ..Default::default()
+        };
+        config.files.insert(version.clone(), FileConfig::default());
+        let bumper = Bumper::new(config, &config_path).with_repo_path(dir.path());
+        let git = GitOptions {
+            tag: true,
+            ..Default::default()
+        };
+
+        // Tagging without a commit leaves the written files uncommitted
+        let new_version = bumper.next_version(&Part::Minor, None, false).unwrap();
+        bumper
+            .plan(new_version.clone(), &git)
+            .unwrap()
+            .apply()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&version).unwrap(), "0.2.0");
+        assert!(repo.find_reference("refs/tags/v0.2.0").is_ok());
+
+        // The tree is no longer clean, and the tag exists
+        let err = bumper.plan(new_version.clone(), &git).unwrap_err();
+        assert!(matches!(err, ReleaseError::Git(GitOperationError::Dirty)));
+        let git = GitOptions {
+            allow_dirty: true,
+            ..git
+        };
+        let err = bumper.plan(new_version, &git).unwrap_err();
+        assert!(matches!(
+            err,
+            ReleaseError::Git(GitOperationError::TagExists { .. })
+        ));
+    }
+
+    #[test]
+    fn test_repo_path() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let config_path = dir.path().join("incrementor.toml");
+        fs::write(dir.path().join("VERSION"), "3.1.0").unwrap();
+        fs::write(&config_path, "current_version = \"3.1.0\"\n").unwrap();
+        fs::write(
+            dir.path().join("CHANGELOG.md"),
+            "## [Unreleased]\n\n- A fix\n",
+        )
+        .unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        commit(&repo, "Initial commit");
+
+        // Relative paths are files of the repository, not of the working directory
+        let mut config = Config {
+            current_version: "3.1.0".to_string(),
+            keep_a_changelog: Some(Default::default()),
+            ..Default::default()
+        };
+        config
+            .files
+            .insert(PathBuf::from("VERSION"), FileConfig::default());
+        let bumper = Bumper::new(config, &config_path).with_repo_path(dir.path());
+        let git = GitOptions {
+            commit: true,
+            commit_message: "Release {new_version}".to_string(),
+            ..Default::default()
+        };
+        let new_version = bumper.next_version(&Part::Minor, None, false).unwrap();
+        bumper.plan(new_version, &git).unwrap().apply().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("VERSION")).unwrap(),
+            "3.2.0"
+        );
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message(), Some("Release 3.2.0\n"));
+        let statuses = repo.statuses(None).unwrap();
+        assert!(statuses.is_empty(), "all changed files are committed");
+    }
+
+    #[test]
+    fn test_tag_version() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let commit = commit(&repo, "Initial commit");
+        let object = repo.find_object(commit, None).unwrap();
+        for tag in ["v0.9.0", "v1.10.0", "v1.2.0", "v2.0", "other-3.0.0"] {
+            repo.tag_lightweight(tag, &object, false).unwrap();
+        }
+
+        let config_path = dir.path().join("incrementor.toml");
+        fs::write(&config_path, "current_version = \"0.1.0\"\n").unwrap();
+        let config = Config {
+            current_version: "0.1.0".to_string(),
+            tag_source: TagSource {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bumper = Bumper::new(config, &config_path).with_repo_path(dir.path());
+        bumper.use_tag_version().unwrap();
+        assert_eq!(bumper.config().current_version, "1.10.0");
+
+        let plan = bumper
+            .plan(
+                bumper.next_version(&Part::Patch, None, false).unwrap(),
+                &GitOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(plan.new_version.to_string(), "1.10.1");
+        assert_eq!(plan.config_path, None);
+        assert!(plan.changes.is_empty());
+
+        // No matching tag keeps the configured version
+        bumper.config.current_version = "0.1.0".to_string();
+        bumper.config.tag_name = "release-{new_version}".to_string();
+        bumper.use_tag_version().unwrap();
+        assert_eq!(bumper.config().current_version, "0.1.0");
+    }
+
+    #[test]
+    fn test_auto_bump() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let bumper = Bumper::new(
+            Config {
+                current_version: "1.2.0".to_string(),
+                ..Default::default()
+            },
+            dir.path().join("incrementor.toml"),
+        )
+        .with_repo_path(dir.path());
+
+        commit(&repo, "feat!: before the release");
+        let release = commit(&repo, "chore: release 1.2.0");
+        repo.tag_lightweight("v1.2.0", &repo.find_object(release, None).unwrap(), false)
+            .unwrap();
+
+        let err = bumper.auto_bump().unwrap_err();
+        assert!(matches!(err, ReleaseError::NothingToRelease { .. }));
+        assert_eq!(
+            err.to_string(),
+            "No commits since v1.2.0 call for a release"
+        );
+
+        commit(&repo, "fix: a bug");
+        commit(&repo, "feat(cli): a feature\n\nWith a body");
+        commit(&repo, "docs: the feature");
+
+        let bump = bumper.auto_bump().unwrap();
+        assert_eq!(bump.since.as_deref(), Some("v1.2.0"));
+        assert_eq!(bump.part(), Part::Minor);
+        assert_eq!(bump.commits.len(), 3);
+        let drivers: Vec<_> = bump.drivers().map(|c| c.summary.as_str()).collect();
+        assert_eq!(drivers, vec!["feat(cli): a feature"]);
+    }
+}
@@ -0,0 +1,450 @@
+//! Calendar versioning, see <https://calver.org>
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+use crate::{increment, Error, Part, Result};
+
+/// A segment of a calendar version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    /// Full year, e.g. 2006
+    FullYear,
+    /// Short year, e.g. 6, 16, 106
+    ShortYear,
+    /// Zero-padded year, e.g. 06, 16, 106
+    PaddedYear,
+    /// Short month, e.g. 1, 12
+    Month,
+    /// Zero-padded month, e.g. 01, 12
+    PaddedMonth,
+    /// Short ISO week, e.g. 1, 52
+    Week,
+    /// Zero-padded ISO week, e.g. 01, 52
+    PaddedWeek,
+    /// Short day, e.g. 1, 31
+    Day,
+    /// Zero-padded day, e.g. 01, 31
+    PaddedDay,
+    /// Counter for releases within the same date
+    Micro,
+}
+
+/// Format tokens, longer tokens go first so `YYYY` is not read as `YY` twice
+const SEGMENTS: [(&str, Segment); 10] = [
+    ("YYYY", Segment::FullYear),
+    ("YY", Segment::ShortYear),
+    ("0Y", Segment::PaddedYear),
+    ("MM", Segment::Month),
+    ("0M", Segment::PaddedMonth),
+    ("WW", Segment::Week),
+    ("0W", Segment::PaddedWeek),
+    ("DD", Segment::Day),
+    ("0D", Segment::PaddedDay),
+    ("MICRO", Segment::Micro),
+];
+
+impl Segment {
+    fn pattern(&self) -> &'static str {
+        match self {
+            Segment::FullYear => "[0-9]{4}",
+            Segment::ShortYear => "[1-9][0-9]{0,2}|0",
+            Segment::PaddedYear => "[0-9]{2,3}",
+            Segment::Month | Segment::Week | Segment::Day => "[1-9][0-9]?",
+            Segment::PaddedMonth | Segment::PaddedWeek | Segment::PaddedDay => "[0-9]{2}",
+            Segment::Micro => "[0-9]+",
+        }
+    }
+
+    fn is_padded(&self) -> bool {
+        matches!(
+            self,
+            Segment::PaddedYear | Segment::PaddedMonth | Segment::PaddedWeek | Segment::PaddedDay
+        )
+    }
+
+    /// Name of the segment, e.g. `year` for `YYYY`
+    fn name(&self) -> &'static str {
+        match self {
+            Segment::FullYear | Segment::ShortYear | Segment::PaddedYear => "year",
+            Segment::Month | Segment::PaddedMonth => "month",
+            Segment::Week | Segment::PaddedWeek => "week",
+            Segment::Day | Segment::PaddedDay => "day",
+            Segment::Micro => "micro",
+        }
+    }
+
+    /// Value as written in a version
+    fn format(&self, value: u64) -> String {
+        if self.is_padded() {
+            format!("{value:02}")
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn is_week(&self) -> bool {
+        matches!(self, Segment::Week | Segment::PaddedWeek)
+    }
+
+    /// Significance of a date segment, `None` for segments that are not date based
+    fn date_rank(&self) -> Option<usize> {
+        match self {
+            Segment::FullYear | Segment::ShortYear | Segment::PaddedYear => Some(0),
+            Segment::Month | Segment::PaddedMonth => Some(1),
+            Segment::Day | Segment::PaddedDay => Some(2),
+            Segment::Week | Segment::PaddedWeek => Some(3),
+            Segment::Micro => None,
+        }
+    }
+
+    /// Value of the segment for a date, `None` for segments that are not date based
+    fn date_value(&self, date: NaiveDate, iso_year: bool) -> Option<u64> {
+        let year = if iso_year {
+            date.iso_week().year()
+        } else {
+            date.year()
+        } as u64;
+        match self {
+            Segment::FullYear => Some(year),
+            Segment::ShortYear | Segment::PaddedYear => Some(year.saturating_sub(2000)),
+            Segment::Month | Segment::PaddedMonth => Some(date.month() as u64),
+            Segment::Week | Segment::PaddedWeek => Some(date.iso_week().week() as u64),
+            Segment::Day | Segment::PaddedDay => Some(date.day() as u64),
+            Segment::Micro => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Segment(Segment),
+    Separator(String),
+}
+
+/// Format of a calendar version, e.g. `YYYY.0M.MICRO` or `YY.0W.MICRO`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalVerFormat {
+    tokens: Vec<Token>,
+}
+
+impl FromStr for CalVerFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidFormat {
+            format: format.to_string(),
+            reason,
+        };
+        let mut tokens = vec![];
+        let mut rest = format;
+        while !rest.is_empty() {
+            if let Some((name, segment)) = SEGMENTS.iter().find(|(name, _)| rest.starts_with(name))
+            {
+                tokens.push(Token::Segment(*segment));
+                rest = &rest[name.len()..];
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap();
+            if c.is_alphanumeric() {
+                return Err(invalid(format!("unknown CalVer segment at '{rest}'")));
+            }
+            match tokens.last_mut() {
+                Some(Token::Separator(separator)) => separator.push(c),
+                _ => tokens.push(Token::Separator(c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+
+        let segments = tokens.iter().filter(|t| matches!(t, Token::Segment(_)));
+        if segments.count() == 0 {
+            return Err(invalid("no CalVer segments".to_string()));
+        }
+
+        Ok(CalVerFormat { tokens })
+    }
+}
+
+impl CalVerFormat {
+    /// Parse a version in this format
+    pub fn parse(&self, version: &str) -> Result<CalVer> {
+        let pattern = self
+            .tokens
+            .iter()
+            .map(|token| match token {
+                Token::Segment(segment) => format!("({})", segment.pattern()),
+                Token::Separator(separator) => regex::escape(separator),
+            })
+            .collect::<String>();
+        let invalid = |reason: String| Error::InvalidVersion {
+            version: version.to_string(),
+            reason,
+        };
+        // The pattern is built from escaped separators and fixed segment patterns
+        let re = Regex::new(&format!("^{pattern}$")).expect("valid CalVer pattern");
+
+        let captures = re
+            .captures(version)
+            .ok_or_else(|| invalid("it does not match the CalVer format".to_string()))?;
+        let values = captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .map(|m| {
+                m.as_str()
+                    .parse::<u64>()
+                    .map_err(|e| invalid(e.to_string()))
+            })
+            .collect::<Result<Vec<u64>>>()?;
+
+        Ok(CalVer {
+            format: self.clone(),
+            values,
+        })
+    }
+
+    fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.tokens.iter().filter_map(|token| match token {
+            Token::Segment(segment) => Some(segment),
+            Token::Separator(_) => None,
+        })
+    }
+
+    /// Week based formats use the ISO week year, so week 1 is never in the previous year
+    fn uses_iso_year(&self) -> bool {
+        self.segments().any(Segment::is_week)
+    }
+
+    /// The date values as year, month, day and week, whatever order the format has
+    fn date(&self, values: &[u64]) -> [Option<u64>; 4] {
+        let mut date = [None; 4];
+        for (segment, value) in self.segments().zip(values) {
+            if let Some(rank) = segment.date_rank() {
+                date[rank] = Some(*value);
+            }
+        }
+        date
+    }
+}
+
+/// A calendar version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalVer {
+    format: CalVerFormat,
+    /// Value of each segment in the format
+    values: Vec<u64>,
+}
+
+impl CalVer {
+    /// Name and value of each segment, e.g. `("year", "2024")` and `("month", "05")`
+    pub fn parts(&self) -> Vec<(&'static str, String)> {
+        self.format
+            .segments()
+            .zip(self.values.iter())
+            .map(|(segment, value)| (segment.name(), segment.format(*value)))
+            .collect()
+    }
+
+    /// Bump the version to `today`.
+    ///
+    /// The date segments are rolled to `today`, the micro counter is reset when the
+    /// date changed and incremented when it did not.
+    pub fn bump(&self, today: NaiveDate) -> Result<CalVer> {
+        let iso_year = self.format.uses_iso_year();
+        let date_values: Vec<Option<u64>> = self
+            .format
+            .segments()
+            .map(|segment| segment.date_value(today, iso_year))
+            .collect();
+
+        let current_date = self.format.date(&self.values);
+        let new_date = self.format.date(
+            &date_values
+                .iter()
+                .zip(&self.values)
+                .map(|(date_value, value)| date_value.unwrap_or(*value))
+                .collect::<Vec<_>>(),
+        );
+        if new_date < current_date {
+            return Err(Error::InvalidVersion {
+                version: self.to_string(),
+                reason: format!("it is dated after {today}"),
+            });
+        }
+        let date_changed = new_date != current_date;
+
+        let has_micro = self.format.segments().any(|s| *s == Segment::Micro);
+        if !date_changed && !has_micro {
+            return Err(Error::NoOpBump {
+                part: Part::Patch,
+                version: self.to_string(),
+                reason: format!("it is already at {today} and has no MICRO segment to increment"),
+            });
+        }
+
+        let values = self
+            .values
+            .iter()
+            .zip(date_values)
+            .map(|(value, date_value)| match date_value {
+                Some(date_value) => Ok(date_value),
+                None if date_changed => Ok(0),
+                None => increment(*value, &Part::Patch, self),
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(CalVer {
+            format: self.format.clone(),
+            values,
+        })
+    }
+}
+
+/// Versions of the same format are ordered by their date and micro counter
+impl PartialOrd for CalVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.format == other.format).then(|| {
+            let date = |version: &CalVer| version.format.date(&version.values);
+            date(self)
+                .cmp(&date(other))
+                .then_with(|| self.values.cmp(&other.values))
+        })
+    }
+}
+
+impl Display for CalVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut values = self.values.iter();
+        for token in self.format.tokens.iter() {
+            match token {
+                Token::Segment(segment) => {
+                    let value = values.next().ok_or(fmt::Error)?;
+                    f.write_str(&segment.format(*value))?;
+                }
+                Token::Separator(separator) => f.write_str(separator)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let cases = [
+            ("YYYY.MM.MICRO", "2024.5.3"),
+            ("YYYY.0M.MICRO", "2024.05.0"),
+            ("YY.0W.MICRO", "24.07.12"),
+            ("0Y.0M.0D", "06.01.31"),
+            ("YYYY-MM-DD_MICRO", "2024-12-1_4"),
+        ];
+        for (format, version) in cases {
+            let format: CalVerFormat = format.parse().unwrap();
+            assert_eq!(format.parse(version).unwrap().to_string(), version);
+        }
+    }
+
+    #[test]
+    fn test_parts() {
+        let format: CalVerFormat = "YYYY.0M.MICRO".parse().unwrap();
+        let version = format.parse("2024.05.3").unwrap();
+        assert_eq!(
+            version.parts(),
+            vec![
+                ("year", "2024".to_string()),
+                ("month", "05".to_string()),
+                ("micro", "3".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("YYYY.MM.PATCH".parse::<CalVerFormat>().is_err());
+        assert!("..".parse::<CalVerFormat>().is_err());
+
+        let format: CalVerFormat = "YYYY.0M.MICRO".parse().unwrap();
+        assert!(format.parse("2024.5.1").is_err());
+        assert!(format.parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_bump() {
+        let cases = [
+            // Same month, increment micro
+            ("YYYY.MM.MICRO", "2024.5.3", date(2024, 5, 20), "2024.5.4"),
+            // New month, reset micro
+            ("YYYY.MM.MICRO", "2024.5.3", date(2024, 6, 1), "2024.6.0"),
+            ("YYYY.0M.MICRO", "2023.12.3", date(2024, 1, 1), "2024.01.0"),
+            // ISO week year, 2024-12-30 is in week 1 of 2025
+            ("YY.0W.MICRO", "24.52.2", date(2024, 12, 30), "25.01.0"),
+            ("YY.0W.MICRO", "25.01.0", date(2025, 1, 2), "25.01.1"),
+            // Date only
+            ("YYYY.0M.0D", "2024.05.01", date(2024, 5, 2), "2024.05.02"),
+            // Day first, the date is still compared by year, month and day
+            ("0D.0M.YYYY", "31.12.2023", date(2024, 1, 1), "01.01.2024"),
+            (
+                "0D.0M.YYYY.MICRO",
+                "20.05.2024.0",
+                date(2024, 6, 1),
+                "01.06.2024.0",
+            ),
+        ];
+        for (format, before, today, expect) in cases {
+            let format: CalVerFormat = format.parse().unwrap();
+            let version = format.parse(before).unwrap();
+            assert_eq!(version.bump(today).unwrap().to_string(), expect);
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let format: CalVerFormat = "0D.0M.YYYY.MICRO".parse().unwrap();
+        let ordered = [
+            "31.12.2023.4",
+            "01.01.2024.0",
+            "01.01.2024.1",
+            "20.05.2024.0",
+        ];
+        for pair in ordered.windows(2) {
+            let (a, b) = (
+                format.parse(pair[0]).unwrap(),
+                format.parse(pair[1]).unwrap(),
+            );
+            assert!(a < b, "{a} < {b}");
+        }
+    }
+
+    #[test]
+    fn test_bump_failure() {
+        let format: CalVerFormat = "YYYY.0M.0D".parse().unwrap();
+        let version = format.parse("2024.05.01").unwrap();
+        // Nothing to increment on the same date
+        assert!(version.bump(date(2024, 5, 1)).is_err());
+        // Version is in the future
+        assert!(version.bump(date(2024, 4, 30)).is_err());
+        let format: CalVerFormat = "0D.0M.YYYY".parse().unwrap();
+        let version = format.parse("01.06.2024").unwrap();
+        assert!(version.bump(date(2024, 5, 20)).is_err());
+
+        // The micro counter is at its highest value
+        let format: CalVerFormat = "YYYY.MICRO".parse().unwrap();
+        let version = format.parse(&format!("2024.{}", u64::MAX)).unwrap();
+        assert!(matches!(
+            version.bump(date(2024, 5, 1)),
+            Err(Error::UnsupportedPart { .. })
+        ));
+    }
+}
@@ -0,0 +1,349 @@
+//! Render a changelog section from the commits of a release
+
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::conventional::ConventionalCommit;
+use crate::git_operations::Commit;
+use crate::Placeholders;
+
+#[derive(Debug, Error)]
+pub enum ChangelogError {
+    #[error("Marker {marker:?} not found in changelog {}", path.display())]
+    MarkerNotFound { path: PathBuf, marker: String },
+    #[error("No '## [Unreleased]' heading found in changelog {}", path.display())]
+    UnreleasedNotFound { path: PathBuf },
+    #[error("The Unreleased section of changelog {} is empty, set `allow_empty` to release anyway", path.display())]
+    EmptyUnreleased { path: PathBuf },
+}
+
+/// A changelog that gets a new section for every release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChangelogConfig {
+    pub path: PathBuf,
+    /// The new section is inserted below this marker, which is kept for the next release
+    pub marker: String,
+    /// Template of the section, `{current_version}`, `{new_version}`, `{date}` and `{changes}` are replaced
+    pub template: String,
+    /// Heading of each commit type in order, commits of other types are left out
+    pub sections: IndexMap<String, String>,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        ChangelogConfig {
+            path: PathBuf::from("CHANGELOG.md"),
+            marker: "<!-- next-version -->".to_string(),
+            template: "## {new_version} - {date}\n\n{changes}".to_string(),
+            sections: IndexMap::from([
+                ("feat".to_string(), "Features".to_string()),
+                ("fix".to_string(), "Bug Fixes".to_string()),
+                ("perf".to_string(), "Performance".to_string()),
+            ]),
+        }
+    }
+}
+
+impl ChangelogConfig {
+    /// Render the section of a release from its commits, newest first as listed by git
+    pub fn render(
+        &self,
+        placeholders: &Placeholders,
+        date: NaiveDate,
+        commits: &[Commit],
+    ) -> String {
+        placeholders
+            .replace(&self.template)
+            .replace("{date}", &date.format("%Y-%m-%d").to_string())
+            .replace("{changes}", &self.render_changes(commits))
+    }
+
+    /// The commits grouped by type, breaking changes first
+    fn render_changes(&self, commits: &[Commit]) -> String {
+        let commits: Vec<ConventionalCommit> = commits
+            .iter()
+            .rev()
+            .filter_map(|commit| ConventionalCommit::parse(&commit.message))
+            .collect();
+
+        let breaking = (
+            "Breaking Changes",
+            commits.iter().filter(|c| c.breaking).collect(),
+        );
+        let groups = self.sections.iter().map(|(kind, heading)| {
+            let entries = commits.iter().filter(|c| !c.breaking && &c.kind == kind);
+            (heading.as_str(), entries.collect::<Vec<_>>())
+        });
+
+        std::iter::once(breaking)
+            .chain(groups)
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(heading, entries)| {
+                let mut group = format!("### {heading}\n\n");
+                for commit in entries {
+                    match &commit.scope {
+                        Some(scope) => {
+                            group.push_str(&format!("- **{scope}:** {}\n", commit.description))
+                        }
+                        None => group.push_str(&format!("- {}\n", commit.description)),
+                    }
+                }
+                group
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Insert the section below the marker of the changelog contents
+    pub fn insert(&self, contents: &str, section: &str) -> Result<String, ChangelogError> {
+        let Some(start) = contents.find(&self.marker) else {
+            return Err(ChangelogError::MarkerNotFound {
+                path: self.path.clone(),
+                marker: self.marker.clone(),
+            });
+        };
+        let (before, after) = contents.split_at(start + self.marker.len());
+        let section = section.trim_end();
+        match after.trim_start_matches('\n') {
+            "" => Ok(format!("{before}\n\n{section}\n")),
+            after => Ok(format!("{before}\n\n{section}\n\n{after}")),
+        }
+    }
+}
+
+/// A [Keep a Changelog](https://keepachangelog.com) file, its Unreleased section becomes the release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAChangelogConfig {
+    pub path: PathBuf,
+    /// Release even when the Unreleased section has no entries
+    pub allow_empty: bool,
+}
+
+impl Default for KeepAChangelogConfig {
+    fn default() -> Self {
+        KeepAChangelogConfig {
+            path: PathBuf::from("CHANGELOG.md"),
+            allow_empty: false,
+        }
+    }
+}
+
+impl KeepAChangelogConfig {
+    /// Turn the Unreleased section into the section of `new_version` and start a new empty one.
+    ///
+    /// The `[unreleased]` comparison link is moved on to `tag`, the link of the release compares
+    /// `tag` with the tag the Unreleased link compared before.
+    pub fn release(
+        &self,
+        contents: &str,
+        new_version: &str,
+        tag: &str,
+        date: NaiveDate,
+    ) -> Result<String, ChangelogError> {
+        let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+        let heading = lines
+            .iter()
+            .position(|line| is_unreleased_heading(line))
+            .ok_or_else(|| ChangelogError::UnreleasedNotFound {
+                path: self.path.clone(),
+            })?;
+
+        // The section ends at the next heading of a release or the link references
+        let is_empty = lines[heading + 1..]
+            .iter()
+            .take_while(|line| !line.starts_with("## ") && !is_link_reference(line))
+            .all(|line| line.trim().is_empty());
+        if is_empty && !self.allow_empty {
+            return Err(ChangelogError::EmptyUnreleased {
+                path: self.path.clone(),
+            });
+        }
+
+        let date = date.format("%Y-%m-%d");
+        lines.splice(
+            heading..=heading,
+            [
+                "## [Unreleased]".to_string(),
+                String::new(),
+                format!("## [{new_version}] - {date}"),
+            ],
+        );
+
+        if let Some(link) = lines
+            .iter()
+            .position(|line| line.to_lowercase().starts_with("[unreleased]:"))
+        {
+            // e.g. `[unreleased]: https://github.com/owner/repo/compare/v1.1.0...HEAD`
+            let url = lines[link]["[unreleased]:".len()..].trim().to_string();
+            // Tags may contain `/`, e.g. `release/1.1.0`
+            if let Some((base, previous)) = url
+                .strip_suffix("...HEAD")
+                .and_then(|compare| compare.split_once("/compare/"))
+            {
+                let (base_url, previous_ref) = (base.to_string(), previous.to_string());
+                lines[link] = format!("[unreleased]: {base_url}/compare/{tag}...HEAD");
+                lines.insert(
+                    link + 1,
+                    format!("[{new_version}]: {base_url}/compare/{previous_ref}...{tag}"),
+                );
+            }
+        }
+
+        let mut result = lines.join("\n");
+        if contents.ends_with('\n') {
+            result.push('\n');
+        }
+        Ok(result)
+    }
+}
+
+fn is_unreleased_heading(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    line == "## [unreleased]" || line == "## unreleased"
+}
+
+fn is_link_reference(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]: ")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::changelog::{ChangelogConfig, ChangelogError, KeepAChangelogConfig};
+    use crate::git_operations::Commit;
+    use crate::Placeholders;
+
+    #[test]
+    fn test_render_and_insert() {
+        let config = ChangelogConfig::default();
+        // Newest first, like `git log`
+        let commits: Vec<Commit> = [
+            "docs: left out",
+            "fix(cli): second fix",
+            "feat!: breaking feature",
+            "fix: first fix",
+            "feat(api): a feature\n\nWith a body",
+            "Not conventional",
+        ]
+        .iter()
+        .map(|message| Commit {
+            id: "0".repeat(40),
+            message: message.to_string(),
+        })
+        .collect();
+
+        let section = config.render(
+            &Placeholders {
+                current_version: &"1.2.0",
+                new_version: &"1.3.0",
+            },
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            &commits,
+        );
+        assert_eq!(
+            section,
+            "## 1.3.0 - 2024-05-01\n\n\
+             ### Breaking Changes\n\n- breaking feature\n\n\
+             ### Features\n\n- **api:** a feature\n\n\
+             ### Bug Fixes\n\n- first fix\n- **cli:** second fix\n"
+        );
+
+        let changelog = "# Changelog\n\n<!-- next-version -->\n\n## 1.2.0 - 2024-04-01\n";
+        assert_eq!(
+            config.insert(changelog, "## 1.3.0\n\n- a fix\n").unwrap(),
+            "# Changelog\n\n<!-- next-version -->\n\n## 1.3.0\n\n- a fix\n\n## 1.2.0 - 2024-04-01\n"
+        );
+
+        assert_eq!(
+            config
+                .insert("<!-- next-version -->", "## 1.3.0\n")
+                .unwrap(),
+            "<!-- next-version -->\n\n## 1.3.0\n"
+        );
+
+        let err = config.insert("# Changelog\n", &section).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Marker \"<!-- next-version -->\" not found in changelog CHANGELOG.md"
+        );
+    }
+
+    #[test]
+    fn test_keep_a_changelog() {
+        let config = KeepAChangelogConfig::default();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let changelog = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- A feature
+
+## [1.1.0] - 2024-04-01
+
+- Old
+
+[unreleased]: https://github.com/owner/repo/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/owner/repo/compare/v1.0.0...v1.1.0
+";
+        assert_eq!(
+            config.release(changelog, "1.2.0", "v1.2.0", date).unwrap(),
+            "# Changelog
+
+## [Unreleased]
+
+## [1.2.0] - 2024-05-01
+
+### Added
+
+- A feature
+
+## [1.1.0] - 2024-04-01
+
+- Old
+
+[unreleased]: https://github.com/owner/repo/compare/v1.2.0...HEAD
+[1.2.0]: https://github.com/owner/repo/compare/v1.1.0...v1.2.0
+[1.1.0]: https://github.com/owner/repo/compare/v1.0.0...v1.1.0
+"
+        );
+
+        // Tags with a `/` in their name
+        let changelog = "## [Unreleased]\n\n- A fix\n\n\
+                         [unreleased]: https://github.com/owner/repo/compare/release/1.1.0...HEAD\n";
+        assert_eq!(
+            config
+                .release(changelog, "1.2.0", "release/1.2.0", date)
+                .unwrap(),
+            "## [Unreleased]\n\n## [1.2.0] - 2024-05-01\n\n- A fix\n\n\
+             [unreleased]: https://github.com/owner/repo/compare/release/1.2.0...HEAD\n\
+             [1.2.0]: https://github.com/owner/repo/compare/release/1.1.0...release/1.2.0\n"
+        );
+
+        // Empty Unreleased sections are refused unless allowed
+        let empty = "# Changelog\n\n## [Unreleased]\n\n## [1.1.0] - 2024-04-01\n";
+        let err = config.release(empty, "1.2.0", "v1.2.0", date).unwrap_err();
+        assert!(matches!(err, ChangelogError::EmptyUnreleased { .. }));
+        let config = KeepAChangelogConfig {
+            allow_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.release(empty, "1.2.0", "v1.2.0", date).unwrap(),
+            "# Changelog\n\n## [Unreleased]\n\n## [1.2.0] - 2024-05-01\n\n## [1.1.0] - 2024-04-01\n"
+        );
+
+        let err = config
+            .release("# Changelog\n", "1.2.0", "v1.2.0", date)
+            .unwrap_err();
+        assert!(matches!(err, ChangelogError::UnreleasedNotFound { .. }));
+    }
+}
@@ -1,11 +1,32 @@
 use std::path::{Path, PathBuf};
 
-use eyre::Result;
 use figment::providers::{Env, Format, Toml};
 use figment::value::{Dict, Map};
 use figment::{Figment, Metadata, Profile, Provider};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::changelog::{ChangelogConfig, KeepAChangelogConfig};
+use crate::conventional::AutoConfig;
+use crate::{AnyVersion, BumpOptions, Error, Scheme, DEFAULT_PRERELEASE_CHANNELS};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Invalid config: {0}")]
+    Load(Box<figment::Error>),
+    #[error("Invalid TOML: {0}")]
+    Toml(#[from] toml_edit::TomlError),
+    #[error("Config table '{0}' not found")]
+    MissingTable(String),
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        ConfigError::Load(Box::new(err))
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -23,24 +44,103 @@ impl Default for FileConfig {
     }
 }
 
+/// Read the current version from git tags instead of `current_version`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagSource {
+    pub enabled: bool,
+    /// Tag name with a `{version}` placeholder, e.g. `v{version}`. Tags are matched against
+    /// `tag_name` when not set.
+    pub pattern: Option<String>,
+    /// Only use tags reachable from HEAD
+    pub reachable: bool,
+}
+
+/// What is pushed after the bump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushRefs {
+    /// The current branch
+    Branch,
+    /// The new tag
+    Tag,
+    #[default]
+    Both,
+}
+
+/// Push to a remote after committing and tagging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushConfig {
+    pub enabled: bool,
+    pub remote: String,
+    pub refs: PushRefs,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        PushConfig {
+            enabled: false,
+            remote: "origin".to_string(),
+            refs: PushRefs::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
-    pub current_version: semver::Version,
+    pub current_version: String,
+    pub scheme: Scheme,
+    /// Prerelease labels in order, going back to an earlier one requires `--force`
+    pub prerelease_channels: Vec<String>,
     pub commit: bool,
     pub tag: bool,
     pub commit_message: Option<String>,
+    /// Tag name, `{current_version}` and `{new_version}` are replaced. Tags are matched
+    /// against it with `{new_version}` as the version to find previous releases.
+    pub tag_name: String,
+    /// Tag annotation, the tag name when not set
+    pub tag_message: Option<String>,
+    pub sign_commits: bool,
+    pub sign_tags: bool,
+    /// Key to sign with, the `user.signingkey` of git when not set
+    pub signing_key: Option<String>,
+    /// Also refuse a tag that exists on the push remote, not just locally
+    pub check_remote_tags: bool,
     pub files: IndexMap<PathBuf, FileConfig>,
+    /// Take the current version from the highest matching git tag, the config is not rewritten
+    pub tag_source: TagSource,
+    /// Rules of `--auto`, deriving the part from Conventional Commits
+    pub auto: AutoConfig,
+    /// Changelog that gets a section with the commits of every release
+    pub changelog: Option<ChangelogConfig>,
+    /// Keep a Changelog file whose Unreleased section is released
+    pub keep_a_changelog: Option<KeepAChangelogConfig>,
+    pub push: PushConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            current_version: semver::Version::new(0, 0, 0),
+            current_version: "0.0.0".to_string(),
+            scheme: Scheme::default(),
+            prerelease_channels: DEFAULT_PRERELEASE_CHANNELS.map(String::from).to_vec(),
             commit: false,
             tag: false,
             commit_message: None,
+            tag_name: "v{new_version}".to_string(),
+            tag_message: None,
+            sign_commits: false,
+            sign_tags: false,
+            signing_key: None,
+            check_remote_tags: false,
             files: Default::default(),
+            tag_source: Default::default(),
+            auto: Default::default(),
+            changelog: None,
+            keep_a_changelog: None,
+            push: Default::default(),
         }
     }
 }
@@ -48,10 +148,46 @@ impl Default for Config {
 pub static WORKDIR_CONFIG_PATH: &str = "./incrementor.toml";
 
 impl Config {
-    pub fn from<T: Provider>(provider: T) -> Result<Config> {
+    /// The current version parsed with the configured scheme
+    pub fn version(&self) -> Result<AnyVersion, Error> {
+        self.scheme.parse(&self.current_version)
+    }
+
+    /// The version in a tag name, `None` when the tag doesn't match the `tag_source` pattern,
+    /// or `tag_name` without a pattern
+    pub fn tag_version<'a>(&self, tag: &'a str) -> Option<&'a str> {
+        let (prefix, suffix) = match &self.tag_source.pattern {
+            Some(pattern) => pattern.split_once("{version}")?,
+            None => self.tag_name.split_once("{new_version}")?,
+        };
+        tag.strip_prefix(prefix)?
+            .strip_suffix(suffix)
+            .filter(|version| !version.is_empty())
+    }
+
+    /// Options to bump the version with, using the configured prerelease channels
+    pub fn bump_options(&self, build: Option<String>, force: bool) -> BumpOptions {
+        BumpOptions {
+            build,
+            prerelease_channels: self.prerelease_channels.clone(),
+            force,
+            ..Default::default()
+        }
+    }
+
+    pub fn from<T: Provider>(provider: T) -> Result<Config, ConfigError> {
         Ok(Figment::from(provider).extract()?)
     }
 
+    /// Provider for a config file, focused on the incrementor table when it is shared with other tools
+    pub fn file(path: &Path) -> Figment {
+        let figment = Figment::from(Toml::file(path));
+        match config_table(path) {
+            [] => figment,
+            table => figment.focus(&table.join(".")),
+        }
+    }
+
     pub fn figment() -> Figment {
         Figment::from(Config::default())
             .merge(Toml::file(Path::new(WORKDIR_CONFIG_PATH)))
@@ -72,3 +208,144 @@ impl Provider for Config {
         None
     }
 }
+
+/// Path of the table holding the config, for files that are shared with other tools
+pub fn config_table(path: &Path) -> &'static [&'static str] {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => &["package", "metadata", "incrementor"],
+        Some("pyproject.toml") => &["tool", "incrementor"],
+        _ => &[],
+    }
+}
+
+/// Update `current_version` in the config file contents.
+///
+/// Only the value itself is changed, comments, ordering and quoting of everything else are kept.
+pub fn update_current_version(
+    content: &str,
+    table: &[&str],
+    version: &str,
+) -> Result<String, ConfigError> {
+    let mut doc: DocumentMut = content.parse()?;
+
+    let mut current: &mut dyn TableLike = doc.as_table_mut();
+    for key in table {
+        current = current
+            .get_mut(key)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| ConfigError::MissingTable(table.join(".")))?;
+    }
+
+    match current
+        .get_mut("current_version")
+        .and_then(Item::as_value_mut)
+    {
+        Some(existing) => {
+            // Keep the quoting style and surrounding whitespace/comments of the existing value
+            let literal = match existing {
+                Value::String(formatted) => formatted
+                    .as_repr()
+                    .and_then(|repr| repr.as_raw().as_str())
+                    .is_some_and(|raw| raw.starts_with('\'')),
+                _ => false,
+            };
+            let mut new_value: Value = if literal {
+                format!("'{version}'").parse()?
+            } else {
+                Value::from(version)
+            };
+            *new_value.decor_mut() = existing.decor().clone();
+            *existing = new_value;
+        }
+        None => {
+            current.insert("current_version", toml_edit::value(version));
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{config_table, update_current_version, Config, TagSource};
+    use std::path::Path;
+
+    #[test]
+    fn test_update_current_version() {
+        let content = r#"# Our release config
+current_version = "0.1.0" # keep me
+tag = true
+
+[files.VERSION]
+replace = '{new_version}'
+search  = '{current_version}'
+"#;
+        let res = update_current_version(content, &[], "0.2.0").unwrap();
+        assert_eq!(res, content.replace("\"0.1.0\"", "\"0.2.0\""));
+    }
+
+    #[test]
+    fn test_update_current_version_literal_string() {
+        let content = "current_version = '0.1.0'\ncommit = true\n";
+        let res = update_current_version(content, &[], "0.2.0-beta").unwrap();
+        assert_eq!(res, "current_version = '0.2.0-beta'\ncommit = true\n");
+    }
+
+    #[test]
+    fn test_update_current_version_nested() {
+        let content = r#"[package]
+name = "foo"
+version = "0.1.0"
+
+[package.metadata.incrementor]
+current_version = "0.1.0"
+files = { "Cargo.toml" = { search = 'version = "{current_version}"$' } }
+"#;
+        let table = config_table(Path::new("some/dir/Cargo.toml"));
+        let res = update_current_version(content, table, "1.0.0").unwrap();
+        assert_eq!(
+            res,
+            content.replace("current_version = \"0.1.0\"", "current_version = \"1.0.0\"")
+        );
+    }
+
+    #[test]
+    fn test_tag_version() {
+        let config = Config::default();
+        assert_eq!(config.tag_version("v1.2.3"), Some("1.2.3"));
+        assert_eq!(config.tag_version("1.2.3"), None);
+        assert_eq!(config.tag_version("v"), None);
+
+        let config = Config {
+            tag_name: "release/{new_version}-final".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.tag_version("release/2.0.0-final"), Some("2.0.0"));
+        assert_eq!(config.tag_version("release/2.0.0"), None);
+
+        let config = Config {
+            tag_name: "{new_version}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.tag_version("2.0.0"), Some("2.0.0"));
+
+        // The pattern of the tag source takes precedence over `tag_name`
+        let config = Config {
+            tag_source: TagSource {
+                pattern: Some("release-{version}-final".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(config.tag_version("release-2.0.0-final"), Some("2.0.0"));
+        assert_eq!(config.tag_version("release-2.0.0"), None);
+        assert_eq!(config.tag_version("v2.0.0"), None);
+    }
+
+    #[test]
+    fn test_update_current_version_missing_table() {
+        let content = "[package]\nname = \"foo\"\n";
+        let table = config_table(Path::new("Cargo.toml"));
+        assert!(update_current_version(content, table, "1.0.0").is_err());
+    }
+}
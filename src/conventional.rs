@@ -0,0 +1,280 @@
+//! Pick the part to bump from [Conventional Commits](https://www.conventionalcommits.org)
+
+use std::fmt::{self, Display};
+use std::sync::OnceLock;
+
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::git_operations::Commit;
+use crate::Part;
+
+/// How much a commit bumps the version, ordered from no bump to major
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BumpLevel {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl BumpLevel {
+    /// The part to bump, `Part::None` for no bump
+    pub fn part(self) -> Part {
+        match self {
+            BumpLevel::None => Part::None,
+            BumpLevel::Patch => Part::Patch,
+            BumpLevel::Minor => Part::Minor,
+            BumpLevel::Major => Part::Major,
+        }
+    }
+}
+
+impl Display for BumpLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BumpLevel::None => write!(f, "none"),
+            BumpLevel::Patch => write!(f, "patch"),
+            BumpLevel::Minor => write!(f, "minor"),
+            BumpLevel::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// Rules to derive the bump from commit messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoConfig {
+    /// Level of each commit type, other types don't bump the version
+    pub types: IndexMap<String, BumpLevel>,
+    /// Breaking changes only bump the minor version while the major version is 0
+    pub initial_development: bool,
+}
+
+impl Default for AutoConfig {
+    fn default() -> Self {
+        AutoConfig {
+            types: IndexMap::from([
+                ("feat".to_string(), BumpLevel::Minor),
+                ("fix".to_string(), BumpLevel::Patch),
+                ("perf".to_string(), BumpLevel::Patch),
+            ]),
+            initial_development: true,
+        }
+    }
+}
+
+/// A commit message following the Conventional Commits format, e.g. `feat(cli)!: add --auto`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConventionalCommit {
+    /// Commit type, e.g. `feat` or `fix`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub scope: Option<String>,
+    /// Marked with `!` or a `BREAKING CHANGE` footer
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parse a commit message, `None` when the header isn't a Conventional Commit
+    pub fn parse(message: &str) -> Option<Self> {
+        static HEADER: OnceLock<Regex> = OnceLock::new();
+        let header = HEADER.get_or_init(|| {
+            Regex::new(r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<description>.+)$")
+                .unwrap()
+        });
+
+        let mut lines = message.lines();
+        let captures = header.captures(lines.next()?.trim_end())?;
+        let breaking_footer = lines.any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+
+        Some(ConventionalCommit {
+            kind: captures["type"].to_lowercase(),
+            scope: captures
+                .name("scope")
+                .map(|scope| scope.as_str().to_string()),
+            breaking: captures.name("breaking").is_some() || breaking_footer,
+            description: captures["description"].to_string(),
+        })
+    }
+}
+
+/// The level a single commit calls for
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitBump {
+    pub id: String,
+    /// First line of the message
+    pub summary: String,
+    pub level: BumpLevel,
+    /// Why the commit has this level, e.g. `feat` or `breaking change`
+    pub reason: String,
+}
+
+impl Display for CommitBump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.id.get(..7).unwrap_or(&self.id);
+        write!(f, "{id} {} ({}: {})", self.summary, self.reason, self.level)
+    }
+}
+
+/// The bump derived from the commits since the last release
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoBump {
+    /// Tag of the last release, `None` when all commits were considered
+    pub since: Option<String>,
+    pub level: BumpLevel,
+    /// Every commit since the last release
+    pub commits: Vec<CommitBump>,
+}
+
+impl AutoBump {
+    /// Derive the bump of `commits`, `initial_development` is true for a 0.x version
+    pub fn from_commits(
+        since: Option<String>,
+        commits: &[Commit],
+        config: &AutoConfig,
+        initial_development: bool,
+    ) -> Self {
+        let commits: Vec<CommitBump> = commits
+            .iter()
+            .map(|commit| commit_bump(commit, config, initial_development))
+            .collect();
+        let level = commits
+            .iter()
+            .map(|commit| commit.level)
+            .max()
+            .unwrap_or(BumpLevel::None);
+
+        AutoBump {
+            since,
+            level,
+            commits,
+        }
+    }
+
+    pub fn part(&self) -> Part {
+        self.level.part()
+    }
+
+    /// The commits that drove the decision, i.e. those with the highest level
+    pub fn drivers(&self) -> impl Iterator<Item = &CommitBump> {
+        self.commits
+            .iter()
+            .filter(move |commit| commit.level != BumpLevel::None && commit.level == self.level)
+    }
+}
+
+fn commit_bump(commit: &Commit, config: &AutoConfig, initial_development: bool) -> CommitBump {
+    let summary = commit
+        .message
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let (level, reason) = match ConventionalCommit::parse(&commit.message) {
+        Some(parsed) if parsed.breaking && config.initial_development && initial_development => {
+            (BumpLevel::Minor, "breaking change in 0.x".to_string())
+        }
+        Some(parsed) if parsed.breaking => (BumpLevel::Major, "breaking change".to_string()),
+        Some(parsed) => {
+            let level = config
+                .types
+                .get(&parsed.kind)
+                .copied()
+                .unwrap_or(BumpLevel::None);
+            (level, parsed.kind)
+        }
+        None => (BumpLevel::None, "not a conventional commit".to_string()),
+    };
+
+    CommitBump {
+        id: commit.id.clone(),
+        summary,
+        level,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conventional::{AutoBump, AutoConfig, BumpLevel, ConventionalCommit};
+    use crate::git_operations::Commit;
+    use crate::Part;
+
+    fn commits(messages: &[&str]) -> Vec<Commit> {
+        messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| Commit {
+                id: format!("{i:040}"),
+                message: message.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ConventionalCommit::parse("feat(cli)!: add --auto\n\nbody"),
+            Some(ConventionalCommit {
+                kind: "feat".to_string(),
+                scope: Some("cli".to_string()),
+                breaking: true,
+                description: "add --auto".to_string(),
+            })
+        );
+        let fix = ConventionalCommit::parse("fix: typo\n\nBREAKING CHANGE: renamed").unwrap();
+        assert_eq!(fix.kind, "fix");
+        assert_eq!(fix.scope, None);
+        assert!(fix.breaking);
+        assert!(!ConventionalCommit::parse("Fix: typo").unwrap().breaking);
+        assert_eq!(ConventionalCommit::parse("Update README"), None);
+        assert_eq!(ConventionalCommit::parse("feat:missing space"), None);
+    }
+
+    #[test]
+    fn test_auto_bump() {
+        let config = AutoConfig::default();
+
+        let bump = AutoBump::from_commits(
+            Some("v1.2.0".to_string()),
+            &commits(&["fix: a", "feat(api): b", "docs: c", "Merge branch"]),
+            &config,
+            false,
+        );
+        assert_eq!(bump.part(), Part::Minor);
+        let drivers: Vec<_> = bump.drivers().map(|c| c.summary.as_str()).collect();
+        assert_eq!(drivers, vec!["feat(api): b"]);
+        assert_eq!(bump.commits[3].reason, "not a conventional commit");
+
+        let breaking = commits(&["fix: a", "refactor!: b"]);
+        let bump = AutoBump::from_commits(None, &breaking, &config, false);
+        assert_eq!(bump.level, BumpLevel::Major);
+        assert_eq!(bump.commits[1].reason, "breaking change");
+
+        // Breaking changes of a 0.x version bump the minor version
+        let bump = AutoBump::from_commits(None, &breaking, &config, true);
+        assert_eq!(bump.level, BumpLevel::Minor);
+        let config = AutoConfig {
+            initial_development: false,
+            ..Default::default()
+        };
+        let bump = AutoBump::from_commits(None, &breaking, &config, true);
+        assert_eq!(bump.level, BumpLevel::Major);
+
+        let bump = AutoBump::from_commits(None, &commits(&["docs: a", "chore: b"]), &config, false);
+        assert_eq!(bump.part(), Part::None);
+        assert_eq!(bump.drivers().count(), 0);
+
+        // Custom rules
+        let mut config = AutoConfig::default();
+        config.types.insert("docs".to_string(), BumpLevel::Patch);
+        let bump = AutoBump::from_commits(None, &commits(&["docs: a"]), &config, false);
+        assert_eq!(bump.level, BumpLevel::Patch);
+    }
+}
@@ -0,0 +1,438 @@
+//! Custom version schemes, defined by a parse regex, serialize templates and part rules
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Part, Result};
+
+/// Rules for a single part of a custom version
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartConfig {
+    /// Allowed values in order, e.g. `["alpha", "beta", "rc", "final"]`. Numeric when empty.
+    pub values: Vec<String>,
+    /// Value a numeric part is reset to, defaults to `0`
+    pub first_value: Option<String>,
+    /// Value that may be left out when serializing, defaults to the first value
+    pub optional_value: Option<String>,
+    /// Don't reset this part when a part before it is bumped
+    pub independent: bool,
+}
+
+impl PartConfig {
+    fn first_value(&self) -> String {
+        match self.values.first() {
+            Some(value) => value.clone(),
+            None => self.first_value.clone().unwrap_or("0".to_string()),
+        }
+    }
+
+    fn optional_value(&self) -> String {
+        self.optional_value
+            .clone()
+            .unwrap_or_else(|| self.first_value())
+    }
+
+    /// The value after `value`, or the reason there is none
+    fn next_value(&self, value: &str) -> Result<String, String> {
+        if self.values.is_empty() {
+            return increment_numeric(value).ok_or_else(|| format!("'{value}' is not numeric"));
+        }
+        let position = self
+            .values
+            .iter()
+            .position(|v| v == value)
+            .ok_or_else(|| format!("'{value}' is not one of its values"))?;
+        self.values
+            .get(position + 1)
+            .cloned()
+            .ok_or_else(|| format!("it is already at its last value '{value}'"))
+    }
+
+    /// Order of two values, by their position in `values` or else by their first number.
+    ///
+    /// `None` when a value is not one of `values`.
+    fn cmp_values(&self, a: &str, b: &str) -> Option<Ordering> {
+        if a == b {
+            return Some(Ordering::Equal);
+        }
+        if self.values.is_empty() {
+            // Values with the same number, e.g. `r3` and `R3`, still need a stable order
+            let number =
+                |value: &str| number_range(value).and_then(|r| value[r].parse::<u64>().ok());
+            return Some(number(a).cmp(&number(b)).then_with(|| a.cmp(b)));
+        }
+        let position = |value: &str| self.values.iter().position(|v| v == value);
+        Some(position(a)?.cmp(&position(b)?))
+    }
+}
+
+/// Range of the first number in the value
+fn number_range(value: &str) -> Option<Range<usize>> {
+    let start = value.find(|c: char| c.is_ascii_digit())?;
+    let end = value[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(value.len(), |i| start + i);
+    Some(start..end)
+}
+
+/// Increment the first number in the value, keeping any prefix and suffix (e.g. `r3` -> `r4`)
+fn increment_numeric(value: &str) -> Option<String> {
+    let Range { start, end } = number_range(value)?;
+    let number: u64 = value[start..end].parse().ok()?;
+    Some(format!(
+        "{}{}{}",
+        &value[..start],
+        number.checked_add(1)?,
+        &value[end..]
+    ))
+}
+
+/// Format of a custom version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomFormat {
+    parse: String,
+    serialize: Vec<String>,
+    /// Parts in the order of the named groups in the parse regex
+    parts: Vec<(String, PartConfig)>,
+}
+
+impl CustomFormat {
+    pub fn new(
+        parse: &str,
+        serialize: &[String],
+        parts: &BTreeMap<String, PartConfig>,
+    ) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidFormat {
+            format: parse.to_string(),
+            reason,
+        };
+        let re = Regex::new(parse).map_err(|e| invalid(e.to_string()))?;
+        let names: Vec<&str> = re.capture_names().flatten().collect();
+        if names.is_empty() {
+            return Err(invalid(
+                "the parse regex has no named groups, e.g. (?P<major>\\d+)".to_string(),
+            ));
+        }
+        if let Some(name) = parts.keys().find(|name| !names.contains(&name.as_str())) {
+            return Err(invalid(format!("part '{name}' is not a named group")));
+        }
+        if serialize.is_empty() {
+            return Err(invalid(
+                "at least one serialize template is required".to_string(),
+            ));
+        }
+        for template in serialize {
+            if let Some(label) = labels(template).find(|label| !names.contains(label)) {
+                return Err(invalid(format!(
+                    "unknown part '{label}' in serialize template '{template}'"
+                )));
+            }
+        }
+
+        Ok(CustomFormat {
+            parse: parse.to_string(),
+            serialize: serialize.to_vec(),
+            parts: names
+                .into_iter()
+                .map(|name| {
+                    let config = parts.get(name).cloned().unwrap_or_default();
+                    (name.to_string(), config)
+                })
+                .collect(),
+        })
+    }
+
+    /// Parse a version in this format, parts that are not matched get their optional value
+    pub fn parse(&self, version: &str) -> Result<CustomVersion> {
+        // The regex was validated when the format was created
+        let re = Regex::new(&format!("^(?:{})$", self.parse)).expect("valid parse regex");
+        let captures = re.captures(version).ok_or_else(|| Error::InvalidVersion {
+            version: version.to_string(),
+            reason: format!("it does not match '{}'", self.parse),
+        })?;
+
+        let values = self
+            .parts
+            .iter()
+            .map(|(name, config)| match captures.name(name) {
+                Some(m) => m.as_str().to_string(),
+                None => config.optional_value(),
+            })
+            .collect();
+
+        Ok(CustomVersion {
+            format: self.clone(),
+            values,
+        })
+    }
+
+    /// The serialize template for the values.
+    ///
+    /// A template is complete when it contains all parts up to the last part that is not at
+    /// its optional value. The complete template with the least parts is used, or the first
+    /// template when none is complete.
+    fn choose_template(&self, values: &[String]) -> &str {
+        let required = self
+            .parts
+            .iter()
+            .zip(values)
+            .rposition(|((_, config), value)| *value != config.optional_value())
+            .map_or(0, |i| i + 1);
+
+        self.serialize
+            .iter()
+            .filter(|template| {
+                self.parts[..required]
+                    .iter()
+                    .all(|(name, _)| labels(template).any(|label| label == name))
+            })
+            .min_by_key(|template| labels(template).count())
+            .unwrap_or(&self.serialize[0])
+    }
+}
+
+/// Part names used in a template, e.g. `major` for `{major}`
+fn labels(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|s| s.split_once('}').map(|(label, _)| label))
+}
+
+/// A version of a custom format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomVersion {
+    format: CustomFormat,
+    /// Value of each part in the format
+    values: Vec<String>,
+}
+
+impl CustomVersion {
+    /// Value of a part
+    pub fn get(&self, part: &str) -> Option<&str> {
+        self.format
+            .parts
+            .iter()
+            .position(|(name, _)| name == part)
+            .map(|i| self.values[i].as_str())
+    }
+
+    /// Name and value of each part
+    pub fn parts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.format
+            .parts
+            .iter()
+            .zip(self.values.iter())
+            .map(|((name, _), value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Bump a part, resetting all parts after it that are not independent
+    pub fn bump(&self, part: &str) -> Result<CustomVersion> {
+        let unsupported =
+            |reason: String| Error::unsupported_part(&Part::Named(part.to_string()), self, reason);
+        let index = self
+            .format
+            .parts
+            .iter()
+            .position(|(name, _)| name == part)
+            .ok_or_else(|| unsupported("the scheme has no such part".to_string()))?;
+
+        let mut values = self.values.clone();
+        for (i, ((_, config), value)) in self.format.parts.iter().zip(values.iter_mut()).enumerate()
+        {
+            if i == index {
+                *value = config.next_value(value).map_err(unsupported)?;
+            } else if i > index && !config.independent {
+                *value = config.first_value();
+            }
+        }
+
+        Ok(CustomVersion {
+            format: self.format.clone(),
+            values,
+        })
+    }
+}
+
+/// Versions of the same format are ordered by their parts, in the order they are declared
+impl PartialOrd for CustomVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.format != other.format {
+            return None;
+        }
+        self.format
+            .parts
+            .iter()
+            .zip(self.values.iter().zip(other.values.iter()))
+            .map(|((_, config), (a, b))| config.cmp_values(a, b))
+            .find(|ordering| *ordering != Some(Ordering::Equal))
+            .unwrap_or(Some(Ordering::Equal))
+    }
+}
+
+impl Display for CustomVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = self.format.choose_template(&self.values).to_string();
+        for ((name, _), value) in self.format.parts.iter().zip(self.values.iter()) {
+            result = result.replace(&format!("{{{name}}}"), value);
+        }
+        f.write_str(&result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(parse: &str, serialize: &[&str], parts: &[(&str, PartConfig)]) -> CustomFormat {
+        let serialize: Vec<String> = serialize.iter().map(|s| s.to_string()).collect();
+        let parts = parts
+            .iter()
+            .map(|(name, config)| (name.to_string(), config.clone()))
+            .collect();
+        CustomFormat::new(parse, &serialize, &parts).unwrap()
+    }
+
+    #[test]
+    fn test_four_parts() {
+        let format = format(
+            r"(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)\.(?P<build>\d+)",
+            &["{major}.{minor}.{patch}.{build}"],
+            &[(
+                "build",
+                PartConfig {
+                    independent: true,
+                    ..Default::default()
+                },
+            )],
+        );
+        let version = format.parse("4.2.1.1037").unwrap();
+        assert_eq!(version.get("build"), Some("1037"));
+
+        let cases = [
+            ("major", "5.0.0.1037"),
+            ("minor", "4.3.0.1037"),
+            ("patch", "4.2.2.1037"),
+            ("build", "4.2.1.1038"),
+        ];
+        for (part, expect) in cases {
+            assert_eq!(version.bump(part).unwrap().to_string(), expect);
+        }
+    }
+
+    #[test]
+    fn test_optional_part() {
+        let format = format(
+            r"(?P<major>\d+)\.(?P<minor>\d+)(-r(?P<revision>\d+))?",
+            &["{major}.{minor}-r{revision}", "{major}.{minor}"],
+            &[],
+        );
+        let version = format.parse("2.1-r3").unwrap();
+        assert_eq!(version.to_string(), "2.1-r3");
+        assert_eq!(version.bump("revision").unwrap().to_string(), "2.1-r4");
+        assert_eq!(version.bump("minor").unwrap().to_string(), "2.2");
+
+        let version = format.parse("2.1").unwrap();
+        assert_eq!(version.get("revision"), Some("0"));
+        assert_eq!(version.bump("revision").unwrap().to_string(), "2.1-r1");
+    }
+
+    #[test]
+    fn test_values_part() {
+        let format = format(
+            r"(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)(-(?P<release>[a-z]+))?",
+            &[
+                "{major}.{minor}.{patch}-{release}",
+                "{major}.{minor}.{patch}",
+            ],
+            &[(
+                "release",
+                PartConfig {
+                    values: vec!["alpha", "beta", "rc", "final"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    optional_value: Some("final".to_string()),
+                    ..Default::default()
+                },
+            )],
+        );
+
+        let version = format.parse("1.0.0-beta").unwrap();
+        let version = version.bump("release").unwrap();
+        assert_eq!(version.to_string(), "1.0.0-rc");
+        let version = version.bump("release").unwrap();
+        assert_eq!(version.to_string(), "1.0.0");
+        assert!(version.bump("release").is_err());
+        assert_eq!(version.bump("minor").unwrap().to_string(), "1.1.0-alpha");
+    }
+
+    #[test]
+    fn test_ordering() {
+        let format = format(
+            r"(?P<major>\d+)\.(?P<minor>\d+)(-(?P<release>[a-z]+))?(-r(?P<revision>\d+))?",
+            &[
+                "{major}.{minor}-{release}-r{revision}",
+                "{major}.{minor}-{release}",
+                "{major}.{minor}",
+            ],
+            &[(
+                "release",
+                PartConfig {
+                    values: vec!["alpha", "beta", "final"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    optional_value: Some("final".to_string()),
+                    ..Default::default()
+                },
+            )],
+        );
+        let parse = |version: &str| format.parse(version).unwrap();
+
+        // Ordered by the parts in declared order, numbers and values compare by their order
+        let ordered = [
+            "1.9-alpha",
+            "1.9-beta",
+            "1.9-beta-r2",
+            "1.9-beta-r10",
+            "1.9",
+            "1.10-alpha",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(parse(pair[0]) < parse(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(
+            parse("1.9-final").partial_cmp(&parse("1.9")),
+            Some(Ordering::Equal)
+        );
+
+        // Only versions of the same format compare
+        let other = self::format(r"(?P<major>\d+)", &["{major}"], &[]);
+        assert_eq!(parse("1.9").partial_cmp(&other.parse("1").unwrap()), None);
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let serialize = vec!["{major}".to_string()];
+        let parts = BTreeMap::new();
+        assert!(CustomFormat::new(r"\d+", &serialize, &parts).is_err());
+        assert!(CustomFormat::new(r"(?P<major>\d+)", &[], &parts).is_err());
+        let serialize = vec!["{major}.{minor}".to_string()];
+        assert!(CustomFormat::new(r"(?P<major>\d+)", &serialize, &parts).is_err());
+    }
+
+    #[test]
+    fn test_increment_numeric() {
+        assert_eq!(increment_numeric("9"), Some("10".to_string()));
+        assert_eq!(increment_numeric("r3"), Some("r4".to_string()));
+        assert_eq!(increment_numeric("build5x"), Some("build6x".to_string()));
+        assert_eq!(increment_numeric("final"), None);
+    }
+}
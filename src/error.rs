@@ -0,0 +1,47 @@
+use thiserror::Error;
+
+use crate::Part;
+
+/// Errors of parsing and bumping versions
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Error {
+    #[error("Invalid version '{version}': {reason}")]
+    InvalidVersion { version: String, reason: String },
+    #[error("Invalid prerelease '{prerelease}': {reason}")]
+    InvalidPrerelease { prerelease: String, reason: String },
+    #[error("Invalid build metadata '{build}': {reason}")]
+    InvalidBuildMetadata { build: String, reason: String },
+    /// A CalVer format or custom scheme that can't be used
+    #[error("Invalid format '{format}': {reason}")]
+    InvalidFormat { format: String, reason: String },
+    /// The part can't be bumped for this version or scheme
+    #[error("Can't bump {part} of version '{version}': {reason}")]
+    UnsupportedPart {
+        part: Part,
+        version: String,
+        reason: String,
+    },
+    /// Bumping the part would not change the version
+    #[error("Bumping {part} doesn't change version '{version}': {reason}")]
+    NoOpBump {
+        part: Part,
+        version: String,
+        reason: String,
+    },
+}
+
+impl Error {
+    pub(crate) fn unsupported_part(
+        part: &Part,
+        version: &dyn std::fmt::Display,
+        reason: impl Into<String>,
+    ) -> Self {
+        Error::UnsupportedPart {
+            part: part.clone(),
+            version: version.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
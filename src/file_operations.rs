@@ -0,0 +1,228 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use similar::TextDiff;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FileOperationError {
+    #[error("Failed to write {path:?}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+    #[error("Failed to write {path:?}: {source}; rolling back failed for: {}", format_paths(.rollback_failures))]
+    Rollback {
+        path: PathBuf,
+        source: io::Error,
+        rollback_failures: Vec<PathBuf>,
+    },
+}
+
+fn format_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| format!("{p:?}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A pending change of a single file
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// Contents before the change, `None` when the file does not exist yet
+    pub original: Option<String>,
+    pub contents: String,
+    /// Number of replaced versions, 0 when only the config is updated
+    pub replacements: usize,
+}
+
+impl FileChange {
+    /// Unified diff of the change with 3 lines of context, empty when nothing changes
+    pub fn diff(&self) -> String {
+        let path = self.path.display().to_string();
+        let original = self.original.as_deref().unwrap_or_default();
+        let old_header = match self.original {
+            Some(_) => format!("a/{path}"),
+            None => "/dev/null".to_string(),
+        };
+        TextDiff::from_lines(original, &self.contents)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &format!("b/{path}"))
+            .to_string()
+    }
+}
+
+/// Returns true when both paths point to the same file
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Write all changes, each file atomically.
+///
+/// When one of the writes fails, the files that were already written are restored
+/// to their original contents (or removed when they did not exist before).
+pub fn write_all(changes: &[FileChange]) -> Result<(), FileOperationError> {
+    for (i, change) in changes.iter().enumerate() {
+        if let Err(source) = write_atomic(&change.path, &change.contents) {
+            let rollback_failures = rollback(&changes[..i]);
+            let path = change.path.clone();
+            return if rollback_failures.is_empty() {
+                Err(FileOperationError::Write { path, source })
+            } else {
+                Err(FileOperationError::Rollback {
+                    path,
+                    source,
+                    rollback_failures,
+                })
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Restore written files, returns the paths that could not be restored
+fn rollback(written: &[FileChange]) -> Vec<PathBuf> {
+    let mut failures = Vec::new();
+    for change in written.iter().rev() {
+        let res = match &change.original {
+            Some(original) => write_atomic(&change.path, original),
+            None => fs::remove_file(&change.path),
+        };
+        if res.is_err() {
+            failures.push(change.path.clone());
+        }
+    }
+    failures
+}
+
+/// Write to a temporary file next to `path` and rename it over `path`
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{path:?} is not a file"),
+        )
+    })?;
+    let tmp_name = format!(".{}.incrementor.tmp", file_name.to_string_lossy());
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let res = fs::write(&tmp_path, contents)
+        .and_then(|_| match fs::metadata(path) {
+            // Keep the permissions of the file we are replacing
+            Ok(metadata) => fs::set_permissions(&tmp_path, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use eyre::Result;
+    use tempfile::tempdir;
+
+    use crate::file_operations::{write_all, FileChange, FileOperationError};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_write_all() -> Result<()> {
+        let dir = tempdir()?;
+        let version = dir.path().join("VERSION");
+        let config = dir.path().join("incrementor.toml");
+        fs::write(&version, "0.1.0")?;
+
+        write_all(&[
+            FileChange {
+                path: version.clone(),
+                original: Some("0.1.0".to_string()),
+                contents: "0.2.0".to_string(),
+                replacements: 1,
+            },
+            FileChange {
+                path: config.clone(),
+                original: None,
+                contents: "current_version = \"0.2.0\"".to_string(),
+                replacements: 1,
+            },
+        ])?;
+
+        assert_eq!(fs::read_to_string(&version)?, "0.2.0");
+        assert_eq!(fs::read_to_string(&config)?, "current_version = \"0.2.0\"");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        let dir = tempdir()?;
+        let version = dir.path().join("VERSION");
+        let created = dir.path().join("CREATED");
+        fs::write(&version, "0.1.0")?;
+
+        let res = write_all(&[
+            FileChange {
+                path: version.clone(),
+                original: Some("0.1.0".to_string()),
+                contents: "0.2.0".to_string(),
+                replacements: 1,
+            },
+            FileChange {
+                path: created.clone(),
+                original: None,
+                contents: "0.2.0".to_string(),
+                replacements: 1,
+            },
+            FileChange {
+                path: dir.path().join("missing").join("VERSION"),
+                original: Some("0.1.0".to_string()),
+                contents: "0.2.0".to_string(),
+                replacements: 1,
+            },
+        ]);
+
+        assert!(matches!(res, Err(FileOperationError::Write { .. })));
+        assert_eq!(fs::read_to_string(&version)?, "0.1.0");
+        assert!(!created.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() {
+        let change = FileChange {
+            path: PathBuf::from("Cargo.toml"),
+            original: Some("[package]\nname = \"foo\"\nversion = \"0.1.0\"\n".to_string()),
+            contents: "[package]\nname = \"foo\"\nversion = \"0.2.0\"\n".to_string(),
+            replacements: 1,
+        };
+        assert_eq!(
+            change.diff(),
+            r#"--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -1,3 +1,3 @@
+ [package]
+ name = "foo"
+-version = "0.1.0"
++version = "0.2.0"
+"#
+        );
+
+        let unchanged = FileChange {
+            original: Some(change.contents.clone()),
+            ..change
+        };
+        assert_eq!(unchanged.diff(), "");
+    }
+}
@@ -1,8 +1,8 @@
+use std::ffi::OsStr;
+use std::fmt::{self, Display};
 use std::io;
-#[cfg(test)]
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
 
 use thiserror::Error;
 
@@ -10,14 +10,62 @@ use thiserror::Error;
 pub enum GitOperationError {
     #[error("Git working directory is dirty")]
     Dirty,
+    #[error("`{command}` failed with exit code {}: {stderr}", code.map_or("unknown".to_string(), |c| c.to_string()))]
+    CommandFailed {
+        command: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+    #[error(
+        "Unable to sign the {object}, check the signing key and `gpg.format` of git: {stderr}"
+    )]
+    Signing {
+        object: &'static str,
+        stderr: String,
+    },
+    #[error("Tag '{tag}' already exists{}, use --force-tag to replace it", .remote.as_ref().map_or(String::new(), |remote| format!(" on remote '{remote}'")))]
+    TagExists { tag: String, remote: Option<String> },
     #[error("Unknown git error: {0}")]
     Unknown(#[from] io::Error),
 }
 
+/// A commit of the history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    /// Full commit message
+    pub message: String,
+}
+
+/// Which objects are signed, with the configured key of git unless `key` is set
+#[derive(Debug, Clone, Default)]
+pub struct Signing {
+    pub commits: bool,
+    pub tags: bool,
+    /// GPG key id or SSH key, depending on `gpg.format`
+    pub key: Option<String>,
+}
+
+/// Refs to push to a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Push {
+    pub remote: String,
+    /// e.g. `HEAD` for the current branch and `refs/tags/v1.2.0`
+    pub refspecs: Vec<String>,
+}
+
+impl Display for Push {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.remote, self.refspecs.join(" "))
+    }
+}
+
 /// Minimal git functionality used to tag, commit and check dirty repo
-pub(crate) struct Git {
+#[derive(Debug, Clone)]
+pub struct Git {
     allow_dirty: bool,
     path: Option<PathBuf>,
+    signing: Signing,
 }
 
 impl Git {
@@ -26,59 +74,178 @@ impl Git {
         Ok(Git {
             allow_dirty,
             path: None,
+            signing: Signing::default(),
         })
     }
 
-    /// New at path
-    #[cfg(test)]
+    /// New for the repository at path, instead of the working directory
     pub fn new_with_path(path: &Path, allow_dirty: bool) -> Result<Self, GitOperationError> {
         Ok(Git {
             allow_dirty,
             path: Some(path.to_path_buf()),
+            signing: Signing::default(),
         })
     }
 
+    /// Sign the commits and tags that are created
+    pub fn with_signing(mut self, signing: Signing) -> Self {
+        self.signing = signing;
+        self
+    }
+
     /// Returns true if dirty
+    #[cfg(test)]
     pub fn is_dirty(&self) -> bool {
-        self.is_dirty_check().is_err()
+        self.check_clean().is_err()
     }
 
-    /// Tags the latest commit on the current branch
-    pub fn tag(&self, tag: &str, message: &str) -> Result<(), GitOperationError> {
-        self.is_dirty_check()?;
-        self.create_git_cmd()
-            .args(vec!["tag", "-a", tag, "-m", message])
-            .output()?;
+    /// Tags the latest commit on the current branch, an existing tag is replaced with `force`
+    pub fn tag(&self, tag: &str, message: &str, force: bool) -> Result<(), GitOperationError> {
+        let sign = match (self.signing.tags, &self.signing.key) {
+            (false, _) => "-a".to_string(),
+            (true, None) => "-s".to_string(),
+            (true, Some(key)) => format!("--local-user={key}"),
+        };
+        let mut args = vec!["tag", &sign, tag, "-m", message];
+        if force {
+            args.push("--force");
+        }
+        let output = self
+            .run(&args)
+            .map_err(|err| signing_error(err, self.signing.tags, "tag"))?;
+
+        // Git may report that SSH signing failed and still create an unsigned tag
+        if self.signing.tags && !self.is_signed_tag(tag)? {
+            self.run(&["tag", "--delete", tag])?;
+            return Err(GitOperationError::Signing {
+                object: "tag",
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
         Ok(())
     }
 
-    /// Commit all with message
-    pub fn commit(&self, message: &str) -> Result<(), GitOperationError> {
-        let _res = self
-            .create_git_cmd()
-            .args(vec!["commit", "-am", message])
-            .output()?;
+    /// Returns true when the tag object holds a GPG or SSH signature
+    fn is_signed_tag(&self, tag: &str) -> Result<bool, GitOperationError> {
+        let output = self.run(&["cat-file", "tag", &format!("refs/tags/{tag}")])?;
+        Ok(String::from_utf8_lossy(&output.stdout).contains("-----BEGIN "))
+    }
+
+    /// Commit exactly the files at `paths` with message, new files are added and any other
+    /// changes are left out
+    pub fn commit(&self, message: &str, paths: &[PathBuf]) -> Result<(), GitOperationError> {
+        let paths = paths.iter().map(|path| path.as_os_str());
+        let mut args: Vec<&OsStr> = vec!["add".as_ref(), "--".as_ref()];
+        args.extend(paths.clone());
+        self.run(&args)?;
+
+        let sign = match (self.signing.commits, &self.signing.key) {
+            (false, _) => None,
+            (true, None) => Some("--gpg-sign".to_string()),
+            (true, Some(key)) => Some(format!("--gpg-sign={key}")),
+        };
+        let mut args: Vec<&OsStr> = vec!["commit".as_ref()];
+        args.extend(sign.iter().map(OsStr::new));
+        args.extend(["-m", message, "--"].map(OsStr::new));
+        args.extend(paths);
+        self.run(&args)
+            .map_err(|err| signing_error(err, self.signing.commits, "commit"))?;
         Ok(())
     }
 
+    /// Returns true when the tag exists in the repository
+    pub fn tag_exists(&self, tag: &str) -> Result<bool, GitOperationError> {
+        let name = format!("refs/tags/{tag}");
+        match self.run(&["rev-parse", "--quiet", "--verify", &name]) {
+            Ok(_) => Ok(true),
+            // With `--quiet` a missing ref exits with 1 and prints nothing
+            Err(GitOperationError::CommandFailed {
+                code: Some(1),
+                stderr,
+                ..
+            }) if stderr.is_empty() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns true when the tag exists on the remote
+    pub fn remote_tag_exists(&self, remote: &str, tag: &str) -> Result<bool, GitOperationError> {
+        let output = self.run(&["ls-remote", "--tags", remote, &format!("refs/tags/{tag}")])?;
+        Ok(!output.stdout.is_empty())
+    }
+
+    /// Push the refs to the remote
+    pub fn push(&self, push: &Push) -> Result<(), GitOperationError> {
+        let mut args = vec!["push", push.remote.as_str()];
+        args.extend(push.refspecs.iter().map(String::as_str));
+        self.run(&args)?;
+        Ok(())
+    }
+
+    /// Names of all tags, or only the tags reachable from HEAD when `merged` is set
+    pub fn tags(&self, merged: bool) -> Result<Vec<String>, GitOperationError> {
+        let mut args = vec!["tag", "--list"];
+        if merged {
+            args.extend(["--merged", "HEAD"]);
+        }
+        let output = self.run(&args)?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
+    /// Commits after `since` up to HEAD, newest first, or the whole history without `since`
+    pub fn commits_since(&self, since: Option<&str>) -> Result<Vec<Commit>, GitOperationError> {
+        let range = since.map_or("HEAD".to_string(), |since| format!("{since}..HEAD"));
+        // Fields are separated by a unit separator, commits by a record separator
+        let output = self.run(&["log", "--format=%H%x1f%B%x1e", &range])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\x1e')
+            .filter_map(|record| {
+                let (id, message) = record.trim_start().split_once('\x1f')?;
+                Some(Commit {
+                    id: id.to_string(),
+                    message: message.trim().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Run git with the arguments, fails when git exits with a non-zero status
+    fn run<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<Output, GitOperationError> {
+        let output = self.create_git_cmd().args(args).output()?;
+        if output.status.success() {
+            Ok(output)
+        } else {
+            let args: Vec<_> = args
+                .iter()
+                .map(|arg| arg.as_ref().to_string_lossy())
+                .collect();
+            Err(GitOperationError::CommandFailed {
+                command: format!("git {}", args.join(" ")),
+                code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+
     /// Create the git command with default arguments.
     fn create_git_cmd(&self) -> Command {
         let mut cmd = Command::new("git");
         if let Some(path) = &self.path {
-            cmd.args(["-C", path.to_str().unwrap()]);
+            cmd.arg("-C").arg(path);
         }
         cmd
     }
 
-    /// Returns 'true' when the git working directory is dirty (has changes)
-    fn is_dirty_check(&self) -> Result<(), GitOperationError> {
+    /// Fails when the git working directory is dirty (has changes) or git can't be run
+    pub fn check_clean(&self) -> Result<(), GitOperationError> {
         if self.allow_dirty {
             return Ok(());
         }
-        let output = self
-            .create_git_cmd()
-            .args(["status", "--porcelain"])
-            .output()?;
+        let output = self.run(&["status", "--porcelain"])?;
 
         // Check the command's standard output; if it's empty, there are no changes
         if output.stdout.is_empty() {
@@ -89,16 +256,44 @@ impl Git {
     }
 }
 
+/// Errors git reports when the signing program (gpg or ssh-keygen) fails or can't be run
+const SIGNING_FAILURES: [&str; 7] = [
+    "gpg failed to sign",
+    "cannot run gpg",
+    "cannot run ssh-keygen",
+    "Couldn't load public key",
+    "failed to get the ssh fingerprint",
+    "ssh-keygen -Y sign is needed",
+    "gpg.ssh.defaultKeyCommand needs to be configured",
+];
+
+/// A failed command is a signing error when signing was requested and git reports that the
+/// signing program failed
+fn signing_error(err: GitOperationError, signed: bool, object: &'static str) -> GitOperationError {
+    match err {
+        GitOperationError::CommandFailed { stderr, .. }
+            if signed
+                && SIGNING_FAILURES
+                    .iter()
+                    .any(|failure| stderr.contains(failure)) =>
+        {
+            GitOperationError::Signing { object, stderr }
+        }
+        err => err,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
 
     use eyre::Result;
     use git2::Repository;
     use tempfile::tempdir;
 
-    use crate::git_operations::Git;
+    use crate::git_operations::{Git, GitOperationError, Push, Signing};
 
     fn create_file_in_repo(repo_path: &Path, file_name: &str, contents: &str) -> Result<()> {
         let file_path = repo_path.join(file_name);
@@ -129,6 +324,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_signing() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path())?;
+        let repo_path = repo.path().parent().unwrap();
+        create_file_in_repo(repo_path, "VERSION", "0.1.0")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("VERSION"))?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = repo.signature()?;
+        repo.commit(Some("HEAD"), &sig, &sig, "Adding VERSION", &tree, &[])?;
+
+        // Signing fails when git can't sign, e.g. without a working gpg
+        repo.config()?.set_str("gpg.program", "false")?;
+        let git = Git::new_with_path(repo_path, true)?.with_signing(Signing {
+            tags: true,
+            ..Default::default()
+        });
+        let err = git.tag("0.1.0", "v0.1.0", false).unwrap_err();
+        assert!(matches!(
+            err,
+            GitOperationError::Signing { object: "tag", .. }
+        ));
+        // or when gpg can't be run
+        repo.config()?.set_str("gpg.program", "/nonexistent/gpg")?;
+        let err = git.tag("0.1.0", "v0.1.0", false).unwrap_err();
+        assert!(matches!(
+            err,
+            GitOperationError::Signing { object: "tag", .. }
+        ));
+
+        // Other failures are reported as is, even when they mention signing
+        let head = repo.head()?.peel_to_commit()?;
+        repo.tag_lightweight("unsigned", head.as_object(), false)?;
+        let err = git.tag("unsigned", "v0.1.0", false).unwrap_err();
+        assert!(matches!(err, GitOperationError::CommandFailed { .. }));
+
+        // SSH signing with a key that can't be loaded, no unsigned tag is left behind
+        repo.config()?.set_str("gpg.format", "ssh")?;
+        let git = Git::new_with_path(repo_path, true)?.with_signing(Signing {
+            commits: true,
+            tags: true,
+            key: Some(dir.path().join("missing").display().to_string()),
+        });
+        create_file_in_repo(repo_path, "VERSION", "0.2.0")?;
+        let err = git
+            .commit("signed commit", &[PathBuf::from("VERSION")])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GitOperationError::Signing {
+                object: "commit",
+                ..
+            }
+        ));
+        let err = git.tag("0.2.0", "v0.2.0", false).unwrap_err();
+        assert!(matches!(
+            err,
+            GitOperationError::Signing { object: "tag", .. }
+        ));
+        assert!(!git.tag_exists("0.2.0")?);
+
+        // Sign with an SSH key, when ssh-keygen is available
+        let key = dir.path().join("key");
+        let generated = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f", key.to_str().unwrap()])
+            .status();
+        if !generated.is_ok_and(|status| status.success()) {
+            return Ok(());
+        }
+        let git = Git::new_with_path(repo_path, true)?.with_signing(Signing {
+            commits: true,
+            tags: true,
+            key: Some(key.display().to_string()),
+        });
+
+        create_file_in_repo(repo_path, "VERSION", "0.2.0")?;
+        git.commit("signed commit", &[PathBuf::from("VERSION")])?;
+        let commit = repo.head()?.peel_to_commit()?;
+        assert!(repo.extract_signature(&commit.id(), None).is_ok());
+
+        git.tag("0.2.0", "v0.2.0", false)?;
+        let tag = repo.find_reference("refs/tags/0.2.0")?.peel_to_tag()?;
+        assert!(tag.message().unwrap().contains("BEGIN SSH SIGNATURE"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_commit_and_tag() -> Result<()> {
         let dir = tempdir().unwrap();
@@ -154,24 +438,149 @@ mod tests {
             &[],
         )?;
 
-        let mut index = repo.index()?;
+        // A new file and a changed one are committed, other changes are left out
         create_file_in_repo(repo_path, "TEST", "test")?;
-        index.add_path(Path::new("TEST"))?;
-        index.write()?;
+        create_file_in_repo(repo_path, "VERSION", "0.2.0")?;
+        create_file_in_repo(repo_path, "incrementor.toml", "current_version = '0.2.0'")?;
 
         let git = Git::new_with_path(repo_path, true)?;
-        git.commit("test commit")?;
+        git.commit(
+            "test commit",
+            &[PathBuf::from("TEST"), PathBuf::from("VERSION")],
+        )?;
 
         // Verify the commit exist
         let commit = repo.head()?.peel_to_commit()?;
         assert_eq!(commit.message(), Some("test commit\n"));
+        let tree = commit.tree()?;
+        assert!(tree.get_name("TEST").is_some());
+        let statuses = repo.statuses(None)?;
+        let dirty: Vec<_> = statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(String::from))
+            .collect();
+        assert_eq!(dirty, vec!["incrementor.toml".to_string()]);
 
-        git.tag("0.2.0", "v0.2.0")?;
+        // Tagging doesn't check the working directory, that is up to the caller
+        let git = Git::new_with_path(repo_path, false)?;
+        git.tag("0.2.0", "v0.2.0", false)?;
         let tags = repo.tag_names(None)?;
         assert!(
             tags.iter().any(|name| name == Some("0.2.0")),
             "The tag was not found in the list."
         );
+        assert_eq!(git.tags(true)?, vec!["0.2.0".to_string()]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_non_utf8_path() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path())?;
+        let repo_path = repo.path().parent().unwrap();
+        let name = OsStr::from_bytes(b"VERSION-\xff");
+        fs::write(repo_path.join(name), "0.1.0")?;
+
+        let git = Git::new_with_path(repo_path, true)?;
+        git.commit("Adding VERSION", &[PathBuf::from(name)])?;
+        let tree = repo.head()?.peel_to_commit()?.tree()?;
+        assert!(tree
+            .iter()
+            .any(|entry| entry.name_bytes() == name.as_bytes()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_push() -> Result<()> {
+        let remote_dir = tempdir().unwrap();
+        let remote = Repository::init_bare(remote_dir.path())?;
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path())?;
+        let repo_path = repo.path().parent().unwrap();
+        repo.remote("origin", remote_dir.path().to_str().unwrap())?;
+
+        create_file_in_repo(repo_path, "VERSION", "0.1.0")?;
+        let git = Git::new_with_path(repo_path, true)?;
+        git.commit("Adding VERSION", &[PathBuf::from("VERSION")])?;
+        git.tag("v0.1.0", "v0.1.0", false)?;
+        let branch = repo.head()?.name().unwrap().to_string();
+
+        let push = Push {
+            remote: "origin".to_string(),
+            refspecs: vec!["refs/tags/v0.1.0".to_string()],
+        };
+        assert_eq!(push.to_string(), "origin refs/tags/v0.1.0");
+        git.push(&push)?;
+        assert!(remote.find_reference("refs/tags/v0.1.0").is_ok());
+        assert!(git.tag_exists("v0.1.0")?);
+        assert!(!git.tag_exists("v0.2.0")?);
+        assert!(git.remote_tag_exists("origin", "v0.1.0")?);
+        assert!(!git.remote_tag_exists("origin", "v0.2.0")?);
+
+        // Existing tags are only replaced with force
+        assert!(git.tag("v0.1.0", "again", false).is_err());
+        git.tag("v0.1.0", "again", true)?;
+        let tag = repo.find_reference("refs/tags/v0.1.0")?.peel_to_tag()?;
+        assert_eq!(tag.message(), Some("again\n"));
+        assert!(remote.find_reference(&branch).is_err());
+
+        git.push(&Push {
+            remote: "origin".to_string(),
+            refspecs: vec!["HEAD".to_string()],
+        })?;
+        let head = repo.head()?.peel_to_commit()?.id();
+        assert_eq!(remote.find_reference(&branch)?.target(), Some(head));
+
+        // Unknown remote
+        let res = git.push(&Push {
+            remote: "upstream".to_string(),
+            refspecs: vec!["HEAD".to_string()],
+        });
+        assert!(matches!(res, Err(GitOperationError::CommandFailed { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_commands() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path())?;
+        let repo_path = repo.path().parent().unwrap();
+
+        // Nothing to commit
+        let git = Git::new_with_path(repo_path, true)?;
+        let res = git.commit("empty commit", &[]);
+        assert!(matches!(
+            res,
+            Err(GitOperationError::CommandFailed { code: Some(1), .. })
+        ));
+
+        // No commit to tag
+        let res = git.tag("0.1.0", "v0.1.0", false);
+        match res {
+            Err(GitOperationError::CommandFailed {
+                command, stderr, ..
+            }) => {
+                assert_eq!(command, "git tag -a 0.1.0 -m v0.1.0");
+                assert!(!stderr.is_empty());
+            }
+            _ => panic!("Expected tagging without commits to fail"),
+        }
+
+        // Only a missing tag is reported as absent, other failures are errors
+        assert!(!git.tag_exists("0.1.0")?);
+        let not_a_repo = tempdir().unwrap();
+        let git = Git::new_with_path(not_a_repo.path(), true)?;
+        assert!(matches!(
+            git.tag_exists("0.1.0"),
+            Err(GitOperationError::CommandFailed { .. })
+        ));
 
         Ok(())
     }
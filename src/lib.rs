@@ -1,9 +1,27 @@
-use eyre::{eyre, Result};
+use std::fmt::Display;
+
 use regex::Regex;
 use semver::{BuildMetadata, Prerelease, Version};
 use serde::Serialize;
 
-/// Represents a part of a semver version (e.g. major, minor)
+pub use bumper::{Bumper, GitOptions, ReleaseError, ReleasePlan};
+pub use error::{Error, Result};
+pub use scheme::{AnyVersion, BumpOptions, Scheme, DEFAULT_PRERELEASE_CHANNELS};
+
+mod bumper;
+pub mod calver;
+pub mod changelog;
+pub mod config;
+pub mod conventional;
+pub mod custom;
+mod error;
+pub mod file_operations;
+pub mod git_operations;
+pub mod pep440;
+pub mod replace;
+mod scheme;
+
+/// Represents a part of a version (e.g. major, minor)
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Part {
@@ -11,13 +29,46 @@ pub enum Part {
     Minor,
     Patch,
     Prerelease(Option<String>),
+    /// Promote the prerelease to the next channel, e.g. `alpha.3` -> `beta.1`
+    NextPrerelease,
+    /// Bump major and start a prerelease, e.g. `1.4.2` -> `2.0.0-beta.1`.
+    /// Without a label the first prerelease channel is used.
+    Premajor(Option<String>),
+    /// Bump minor and start a prerelease, e.g. `1.4.2` -> `1.5.0-beta.1`
+    Preminor(Option<String>),
+    /// Bump patch and start a prerelease, e.g. `1.4.2` -> `1.4.3-beta.1`
+    Prepatch(Option<String>),
+    /// A part of a scheme by name, e.g. `build`
+    Named(String),
     None,
 }
 
+impl Display for Part {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let with_label = |name: &str, label: &Option<String>| match label {
+            Some(label) => format!("{name} '{label}'"),
+            None => name.to_string(),
+        };
+        f.write_str(&match self {
+            Part::Major => "major".to_string(),
+            Part::Minor => "minor".to_string(),
+            Part::Patch => "patch".to_string(),
+            Part::Prerelease(None) => "release".to_string(),
+            Part::Prerelease(label) => with_label("prerelease", label),
+            Part::NextPrerelease => "next prerelease".to_string(),
+            Part::Premajor(label) => with_label("premajor", label),
+            Part::Preminor(label) => with_label("preminor", label),
+            Part::Prepatch(label) => with_label("prepatch", label),
+            Part::Named(name) => name.clone(),
+            Part::None => "nothing".to_string(),
+        })
+    }
+}
+
 /// Holds replaceable values like {current_version}
 pub struct Placeholders<'a> {
-    pub current_version: &'a Version,
-    pub new_version: &'a Version,
+    pub current_version: &'a dyn Display,
+    pub new_version: &'a dyn Display,
 }
 
 impl<'a> Placeholders<'a> {
@@ -25,8 +76,8 @@ impl<'a> Placeholders<'a> {
         let re_current_version = Regex::new("\\{current_version\\}").unwrap();
         let re_new_version = Regex::new("\\{new_version\\}").unwrap();
 
-        let result = re_current_version.replace(s, self.current_version.to_string());
-        let result = re_new_version.replace(&result, self.new_version.to_string());
+        let result = re_current_version.replace_all(s, self.current_version.to_string());
+        let result = re_new_version.replace_all(&result, self.new_version.to_string());
 
         result.to_string()
     }
@@ -37,59 +88,243 @@ impl<'a> Placeholders<'a> {
 /// This function increments a part of a semver version based on the `Part` it is given.
 /// The `build` arguments allows for additional build information to be added to the incremented version.
 pub fn bump(v: &Version, part: &Part, build: Option<String>) -> Result<Version> {
+    let options = BumpOptions {
+        build,
+        ..Default::default()
+    };
+    bump_semver(v, part, &options)
+}
+
+/// `n + 1` for bumping `part` of `version`, an error instead of an overflow
+pub(crate) fn increment(n: u64, part: &Part, version: &dyn Display) -> Result<u64> {
+    n.checked_add(1)
+        .ok_or_else(|| Error::unsupported_part(part, version, format!("{n} is the highest value")))
+}
+
+/// Bump a part of a semver version, using the prerelease channels and build metadata of the options
+pub(crate) fn bump_semver(v: &Version, part: &Part, options: &BumpOptions) -> Result<Version> {
+    let channels = &options.prerelease_channels;
     let mut new_version = match part {
-        Part::Major => Ok::<Version, semver::Error>(Version::new(v.major + 1, 0, 0)),
-        Part::Minor => Ok(Version::new(v.major, v.minor + 1, 0)),
-        Part::Patch => Ok(Version::new(v.major, v.minor, v.patch + 1)),
+        Part::Major => Version::new(increment(v.major, part, v)?, 0, 0),
+        Part::Minor => Version::new(v.major, increment(v.minor, part, v)?, 0),
+        Part::Patch => Version::new(v.major, v.minor, increment(v.patch, part, v)?),
         Part::Prerelease(None) => {
-            let mut new_version = v.clone();
             if v.pre.is_empty() {
-                return Err(eyre!(
-                    "Can't remove a 'prerelease' (x.x.x-<prerelease>) from version '{}', missing prerelease", v.to_string()
-                ));
+                return Err(Error::NoOpBump {
+                    part: part.clone(),
+                    version: v.to_string(),
+                    reason: "it has no prerelease to remove".to_string(),
+                });
             }
+            let mut new_version = v.clone();
             new_version.pre = Prerelease::EMPTY;
             return Ok(new_version);
         }
         Part::Prerelease(Some(label)) => {
+            if !options.force {
+                check_channel_order(v, part, label, channels)?;
+            }
+            let existing = parse_prerelease(&v.pre)?;
+            let pre = match &existing {
+                // same label, or a label extending it like `beta.x` for `beta`: increment its
+                // number or start at 1, a shorter label would sort before the current one
+                Some(existing) if extends_label(existing.label, label) => match existing.number {
+                    Some(n) => {
+                        let n = n.checked_add(1).ok_or_else(|| Error::InvalidPrerelease {
+                            prerelease: v.pre.to_string(),
+                            reason: "its number is too large to increment".to_string(),
+                        })?;
+                        format!("{}{}", make_prerelease(existing.label, n), existing.suffix)
+                    }
+                    None => make_prerelease(existing.label, 1),
+                },
+                // no or a different prerelease, start the new label
+                _ => label.clone(),
+            };
             let mut new_version = v.clone();
-            let pre = match parse_prerelease(v.pre.as_str()) {
-                // old version has same prerelease, but no version
-                (Some(existing), None) if &existing == label => {
-                    Prerelease::new(&make_prerelease(label, 1))?
-                }
-                // old version has same prerelease and version
-                (Some(existing), Some(v)) if &existing == label => {
-                    Prerelease::new(&make_prerelease(label, v + 1))?
+            new_version.pre = new_prerelease(&pre)?;
+
+            // Within a channel the prerelease must move forward, e.g. not `beta.foo.2` -> `beta.bar`
+            let same_channel = existing.as_ref().is_some_and(|existing| {
+                existing.channel() == label.split('.').next().unwrap_or(label)
+            });
+            if same_channel && new_version.pre <= v.pre {
+                return Err(Error::unsupported_part(
+                    part,
+                    v,
+                    format!("prerelease '{pre}' would not be greater than '{}'", v.pre),
+                ));
+            }
+            new_version
+        }
+        Part::NextPrerelease => {
+            let label = match parse_prerelease(&v.pre)? {
+                Some(existing) if !existing.label.is_empty() => existing.channel(),
+                _ => {
+                    return Err(Error::unsupported_part(
+                        part,
+                        v,
+                        "it has no prerelease to promote",
+                    ))
                 }
-                // old version has different version
-                (Some(existing), _) if &existing != label => Prerelease::new(label)?,
-                // unknown, return empty
-                _ => Prerelease::EMPTY,
             };
-            new_version.pre = pre;
-            Ok(new_version)
+            let index = channels.iter().position(|c| c == label).ok_or_else(|| {
+                Error::unsupported_part(
+                    part,
+                    v,
+                    format!("'{label}' is not one of the prerelease channels {channels:?}"),
+                )
+            })?;
+            let next = channels.get(index + 1).ok_or_else(|| {
+                Error::unsupported_part(
+                    part,
+                    v,
+                    format!("'{label}' is the last prerelease channel, use release to remove it"),
+                )
+            })?;
+
+            let mut new_version = v.clone();
+            new_version.pre = new_prerelease(&make_prerelease(next, 1))?;
+            new_version
+        }
+        Part::Premajor(label) | Part::Preminor(label) | Part::Prepatch(label) => {
+            let mut new_version = match part {
+                Part::Premajor(_) => Version::new(increment(v.major, part, v)?, 0, 0),
+                Part::Preminor(_) => Version::new(v.major, increment(v.minor, part, v)?, 0),
+                _ => Version::new(v.major, v.minor, increment(v.patch, part, v)?),
+            };
+            let label = match label {
+                Some(label) => label,
+                None => channels.first().ok_or_else(|| {
+                    Error::unsupported_part(
+                        part,
+                        v,
+                        "no prerelease label given and no prerelease channels configured",
+                    )
+                })?,
+            };
+            new_version.pre = new_prerelease(&make_prerelease(label, 1))?;
+            new_version
+        }
+        Part::Named(name) => {
+            return Err(Error::unsupported_part(
+                part,
+                v,
+                format!("SemVer versions have no part '{name}'"),
+            ));
+        }
+        Part::None => {
+            return Err(Error::NoOpBump {
+                part: part.clone(),
+                version: v.to_string(),
+                reason: "no part to bump was given".to_string(),
+            })
         }
-        // TODO: Fix error return type
-        Part::None => panic!("impossible"),
-    }?;
+    };
 
     // Add build metadata
-    new_version.build = build.map_or(BuildMetadata::EMPTY, |x| BuildMetadata::new(&x).unwrap());
+    new_version.build = match &options.build {
+        Some(build) => BuildMetadata::new(build).map_err(|e| Error::InvalidBuildMetadata {
+            build: build.clone(),
+            reason: e.to_string(),
+        })?,
+        None => BuildMetadata::EMPTY,
+    };
 
     Ok(new_version)
 }
 
-fn parse_prerelease(s: &str) -> (Option<String>, Option<u64>) {
-    let splits: Vec<&str> = s.split('.').collect();
-    if let Some(label) = splits.first() {
-        if let Some(version_string) = splits.get(1) {
-            let version: u64 = version_string.parse().unwrap();
-            return (Some(label.to_string()), Some(version));
-        }
-        return (Some(label.to_string()), None);
+/// Returns true when `existing` is `label` or starts with all identifiers of `label`
+fn extends_label(existing: &str, label: &str) -> bool {
+    existing == label
+        || existing
+            .strip_prefix(label)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Fails when the prerelease `label` is in an earlier channel than the prerelease of `v`
+fn check_channel_order(v: &Version, part: &Part, label: &str, channels: &[String]) -> Result<()> {
+    let Some(existing) = parse_prerelease(&v.pre)? else {
+        return Ok(());
+    };
+    let existing = existing.channel();
+    let label = label.split('.').next().unwrap_or(label);
+    let position = |label: &str| channels.iter().position(|c| c == label);
+    match (position(existing), position(label)) {
+        (Some(current), Some(new)) if new < current => Err(Error::unsupported_part(
+            part,
+            v,
+            format!("can't go back from prerelease '{existing}' to '{label}', use force to do it anyway"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A prerelease split around its last numeric identifier, e.g. `beta.feature-x.3` or `rc.1.hotfix`
+#[derive(Debug, PartialEq, Eq)]
+struct PrereleaseParts<'a> {
+    /// Identifiers before the number, e.g. `beta.feature-x`
+    label: &'a str,
+    /// The last numeric identifier, `None` when there is none
+    number: Option<u64>,
+    /// Identifiers after the number including the leading dot, e.g. `.hotfix`
+    suffix: &'a str,
+}
+
+impl<'a> PrereleaseParts<'a> {
+    /// The first identifier of the label, which is matched against the prerelease channels
+    fn channel(&self) -> &'a str {
+        self.label.split('.').next().unwrap_or(self.label)
+    }
+}
+
+/// Split a prerelease around its last numeric identifier, `None` when there is no prerelease
+fn parse_prerelease(pre: &Prerelease) -> Result<Option<PrereleaseParts<'_>>> {
+    let s = pre.as_str();
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    let identifiers: Vec<&str> = s.split('.').collect();
+    let Some(index) = identifiers
+        .iter()
+        .rposition(|i| i.bytes().all(|b| b.is_ascii_digit()))
+    else {
+        return Ok(Some(PrereleaseParts {
+            label: s,
+            number: None,
+            suffix: "",
+        }));
+    };
+
+    let number = identifiers[index]
+        .parse()
+        .map_err(|_| Error::InvalidPrerelease {
+            prerelease: s.to_string(),
+            reason: format!("number '{}' is too large", identifiers[index]),
+        })?;
+    // Byte offsets of the number within `s`
+    let start: usize = identifiers[..index].iter().map(|i| i.len() + 1).sum();
+    let end = start + identifiers[index].len();
+    Ok(Some(PrereleaseParts {
+        label: s[..start].trim_end_matches('.'),
+        number: Some(number),
+        suffix: &s[end..],
+    }))
+}
+
+/// Create a prerelease, with a descriptive error for labels SemVer does not allow
+fn new_prerelease(pre: &str) -> Result<Prerelease> {
+    if pre.is_empty() {
+        return Err(Error::InvalidPrerelease {
+            prerelease: pre.to_string(),
+            reason: "the label must not be empty".to_string(),
+        });
     }
-    (None, None)
+    Prerelease::new(pre).map_err(|e| Error::InvalidPrerelease {
+        prerelease: pre.to_string(),
+        reason: e.to_string(),
+    })
 }
 
 fn make_prerelease(label: &str, version: u64) -> String {
@@ -162,6 +397,40 @@ mod tests {
             ),
             // release
             ("1.3.3-nightly.999", Part::Prerelease(None), None, "1.3.3"),
+            // Non-numeric identifiers are part of the label
+            (
+                "1.0.0-beta.x",
+                Part::Prerelease(Some("beta.x".to_string())),
+                None,
+                "1.0.0-beta.x.1",
+            ),
+            // Multi-segment label
+            (
+                "1.0.0-beta.feature-x.3",
+                Part::Prerelease(Some("beta.feature-x".to_string())),
+                None,
+                "1.0.0-beta.feature-x.4",
+            ),
+            // The last numeric identifier is incremented, the rest is kept
+            (
+                "1.0.0-rc.1.hotfix",
+                Part::Prerelease(Some("rc".to_string())),
+                None,
+                "1.0.0-rc.2.hotfix",
+            ),
+            // A label extending the given one is continued, `beta` would sort before it
+            (
+                "1.0.0-beta.x",
+                Part::Prerelease(Some("beta".to_string())),
+                None,
+                "1.0.0-beta.x.1",
+            ),
+            (
+                "1.0.0-beta.feature-x.3",
+                Part::Prerelease(Some("beta".to_string())),
+                None,
+                "1.0.0-beta.feature-x.4",
+            ),
         ];
 
         for (before, part, build, expect) in cases {
@@ -191,9 +460,183 @@ mod tests {
         let cases = [
             ("{current_version}", "1.0.0-alpha.1+something"),
             ("{new_version}", "2.0.0"),
+            (
+                "Release {new_version} (was {current_version}), see {new_version}",
+                "Release 2.0.0 (was 1.0.0-alpha.1+something), see 2.0.0",
+            ),
         ];
         for (input, expect) in cases {
             assert_eq!(placeholders.replace(input), expect)
         }
     }
+
+    #[test]
+    fn test_parse_prerelease() {
+        let parts = |label, number, suffix| PrereleaseParts {
+            label,
+            number,
+            suffix,
+        };
+        let cases = [
+            ("", None),
+            ("beta", Some(parts("beta", None, ""))),
+            ("beta.2", Some(parts("beta", Some(2), ""))),
+            ("beta.x", Some(parts("beta.x", None, ""))),
+            ("rc.1.hotfix", Some(parts("rc", Some(1), ".hotfix"))),
+            (
+                "beta.feature-x.3",
+                Some(parts("beta.feature-x", Some(3), "")),
+            ),
+            ("1.2", Some(parts("1", Some(2), ""))),
+            ("7", Some(parts("", Some(7), ""))),
+        ];
+        for (pre, expect) in cases {
+            let pre = Prerelease::new(pre).unwrap();
+            assert_eq!(parse_prerelease(&pre).unwrap(), expect);
+        }
+    }
+
+    #[test]
+    fn test_prerelease_errors() {
+        let cases = [
+            // Number does not fit in u64
+            ("1.0.0-beta.99999999999999999999", "beta"),
+            // Invalid label
+            ("1.0.0", "beta!"),
+            ("1.0.0", ""),
+        ];
+        for (before, label) in cases {
+            let version = Version::parse(before).unwrap();
+            let part = Part::Prerelease(Some(label.to_string()));
+            assert!(matches!(
+                bump(&version, &part, None),
+                Err(Error::InvalidPrerelease { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let version = Version::parse("1.2.3").unwrap();
+        assert!(matches!(
+            bump(&version, &Part::Patch, Some("a!b".to_string())),
+            Err(Error::InvalidBuildMetadata { .. })
+        ));
+        assert!(matches!(
+            bump(&version, &Part::Named("build".to_string()), None),
+            Err(Error::UnsupportedPart { .. })
+        ));
+        // A prerelease of the same channel that would sort before the current one
+        assert!(matches!(
+            bump(
+                &Version::parse("1.0.0-beta.foo.2").unwrap(),
+                &Part::Prerelease(Some("beta.bar".to_string())),
+                None
+            ),
+            Err(Error::UnsupportedPart { .. })
+        ));
+        assert!(matches!(
+            bump(&version, &Part::NextPrerelease, None),
+            Err(Error::UnsupportedPart { .. })
+        ));
+        assert!(matches!(
+            bump(&version, &Part::Prerelease(None), None),
+            Err(Error::NoOpBump { .. })
+        ));
+        assert_eq!(
+            bump(&version, &Part::None, None),
+            Err(Error::NoOpBump {
+                part: Part::None,
+                version: "1.2.3".to_string(),
+                reason: "no part to bump was given".to_string(),
+            })
+        );
+
+        // Parts at the highest value can't be incremented
+        let max = Version::new(u64::MAX, u64::MAX, u64::MAX);
+        for part in [Part::Major, Part::Minor, Part::Patch, Part::Prepatch(None)] {
+            assert!(matches!(
+                bump(&max, &part, None),
+                Err(Error::UnsupportedPart { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_prerelease_channels() {
+        let options = BumpOptions::default();
+        let cases = [
+            ("1.2.0-alpha.3", Part::NextPrerelease, "1.2.0-beta.1"),
+            ("1.2.0-beta", Part::NextPrerelease, "1.2.0-rc.1"),
+            (
+                "1.2.0-alpha.3",
+                Part::Prerelease(Some("rc".to_string())),
+                "1.2.0-rc",
+            ),
+        ];
+        for (before, part, expect) in cases {
+            let version = Version::parse(before).unwrap();
+            let expected_version = Version::parse(expect).unwrap();
+            assert_eq!(
+                bump_semver(&version, &part, &options).unwrap(),
+                expected_version
+            )
+        }
+
+        let failures = [
+            ("1.2.0", Part::NextPrerelease),
+            ("1.2.0-rc.2", Part::NextPrerelease),
+            ("1.2.0-nightly.2", Part::NextPrerelease),
+            ("1.2.0-rc.2", Part::Prerelease(Some("alpha".to_string()))),
+        ];
+        for (before, part) in failures {
+            let version = Version::parse(before).unwrap();
+            assert!(bump_semver(&version, &part, &options).is_err())
+        }
+
+        // Going back is allowed when forced
+        let options = BumpOptions {
+            force: true,
+            ..Default::default()
+        };
+        let version = Version::parse("1.2.0-rc.2").unwrap();
+        let part = Part::Prerelease(Some("alpha".to_string()));
+        assert_eq!(
+            bump_semver(&version, &part, &options).unwrap(),
+            Version::parse("1.2.0-alpha").unwrap()
+        );
+
+        // Compound bumps start a new prerelease
+        let cases = [
+            ("1.4.2", Part::Premajor(None), "2.0.0-alpha.1"),
+            (
+                "1.4.2",
+                Part::Preminor(Some("beta".to_string())),
+                "1.5.0-beta.1",
+            ),
+            (
+                "1.4.2-rc.1",
+                Part::Prepatch(Some("beta".to_string())),
+                "1.4.3-beta.1",
+            ),
+        ];
+        for (before, part, expect) in cases {
+            let version = Version::parse(before).unwrap();
+            assert_eq!(
+                bump_semver(&version, &part, &options).unwrap(),
+                Version::parse(expect).unwrap()
+            );
+        }
+
+        // Custom channels
+        let options = BumpOptions {
+            prerelease_channels: vec!["dev".to_string(), "staging".to_string()],
+            ..Default::default()
+        };
+        let version = Version::parse("1.2.0-dev.4").unwrap();
+        assert_eq!(
+            bump_semver(&version, &Part::NextPrerelease, &options).unwrap(),
+            Version::parse("1.2.0-staging.1").unwrap()
+        );
+    }
 }
@@ -1,95 +1,223 @@
 //! # Incrementor
 //! A simple agnostic version bumping tool.
 
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-
-use clap::{ArgGroup, Parser, ValueEnum};
-use eyre::{eyre, Context, Result};
-use figment::providers::{Format, Toml};
-use regex::RegexBuilder;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anstyle::{AnsiColor, Style};
+use clap::{Parser, Subcommand, ValueEnum};
+use eyre::{eyre, Result, WrapErr};
+use figment::providers::Serialized;
+use indexmap::IndexMap;
 use semver::Version;
 use serde::Serialize;
+use toml_edit::{DocumentMut, Item, Table};
 
-use incrementor::{bump, Part, Placeholders};
-
-use crate::config::{Config, FileConfig, WORKDIR_CONFIG_PATH};
-use crate::git_operations::Git;
-
-mod config;
-mod git_operations;
+use incrementor::config::{Config, FileConfig, WORKDIR_CONFIG_PATH};
+use incrementor::conventional::AutoBump;
+use incrementor::git_operations::Signing;
+use incrementor::replace::{check_file, FileCheck};
+use incrementor::{AnyVersion, Bumper, GitOptions, Part};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum OutputFormat {
+    /// Human readable report, coloured on a terminal
+    Text,
     Json,
+    /// Only the new version
+    Version,
+    /// `KEY=value` lines, e.g. `NEW_VERSION=1.2.0` and `NEW_MAJOR=1`
+    Dotenv,
+    /// Append `key=value` lines to the file named by `--output-env`, `$GITHUB_OUTPUT` by default
+    GithubOutput,
+    /// No output
     None,
 }
 
+const BOLD: Style = Style::new().bold();
+const PATH: Style = AnsiColor::Cyan.on_default();
+const GIT: Style = AnsiColor::Yellow.on_default();
+const ADDED: Style = AnsiColor::Green.on_default();
+const REMOVED: Style = AnsiColor::Red.on_default();
+
 #[derive(Debug, Clone, Serialize)]
 struct FileOutput {
-    contents: String,
+    /// Contents after the bump, left out of dry runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contents: Option<String>,
+    /// Number of replaced versions, 0 for a generated change like the changelog
+    replacements: usize,
+    /// Unified diff of the change
+    diff: String,
+}
+
+/// Update of `current_version` in the config file
+#[derive(Debug, Clone, Serialize)]
+struct ConfigOutput<'a> {
+    path: &'a Path,
+    current_version: &'a AnyVersion,
+    new_version: &'a AnyVersion,
 }
 
 #[derive(Debug, Clone, Serialize)]
 struct Output<'a> {
     dry_run: bool,
     part: &'a Part,
+    /// Commits the part was derived from with `--auto`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto: Option<&'a AutoBump>,
     build_metadata: Option<String>,
-    current_version: &'a Version,
-    new_version: &'a Version,
-    files: HashMap<&'a str, FileOutput>,
+    current_version: &'a AnyVersion,
+    new_version: &'a AnyVersion,
+    files: IndexMap<&'a str, FileOutput>,
+    /// `None` when the version is read from git tags
+    config: Option<ConfigOutput<'a>>,
+    /// Tag that is (or in a dry run would be) created
     git_tag: Option<String>,
+    /// Commit message that is (or in a dry run would be) used
     git_commit_message: Option<String>,
+    /// Remote and refs that are (or in a dry run would be) pushed
+    git_push: Option<String>,
 }
 
 impl<'a> Output<'a> {
-    fn print(&self, format: OutputFormat) {
-        match format {
+    fn print(&self, global: &GlobalArgs) -> Result<()> {
+        if let (Some(auto), OutputFormat::Text) = (self.auto, &global.output) {
+            print_auto(auto);
+        }
+        match global.output {
             OutputFormat::Json => {
                 let json = serde_json::to_string_pretty(&self).unwrap();
                 println!("{json}");
             }
-            _ => {
-                // Do nothing
+            OutputFormat::Text if self.dry_run => self.print_preview(),
+            OutputFormat::Text => anstream::print!("{}", self.format_text()),
+            _ => print_versions(global, self.current_version, self.new_version)?,
+        }
+        Ok(())
+    }
+
+    /// The new version, the touched files and the git results, styled for a terminal
+    fn format_text(&self) -> String {
+        let mut lines = vec![format!(
+            "Bumped {} → {BOLD}{}{BOLD:#}",
+            self.current_version, self.new_version
+        )];
+        for (path, file) in self.files.iter() {
+            lines.push(match file.replacements {
+                // Not a search and replace, e.g. the changelog
+                0 => format!("  {PATH}{path}{PATH:#}: updated"),
+                1 => format!("  {PATH}{path}{PATH:#}: 1 replacement"),
+                n => format!("  {PATH}{path}{PATH:#}: {n} replacements"),
+            });
+        }
+        if let Some(config) = &self.config {
+            lines.push(format!(
+                "  {PATH}{}{PATH:#}: current_version updated",
+                config.path.display()
+            ));
+        }
+        if let Some(message) = &self.git_commit_message {
+            lines.push(format!("{GIT}Committed:{GIT:#} {message}"));
+        }
+        if let Some(tag) = &self.git_tag {
+            lines.push(format!("{GIT}Tagged:{GIT:#} {tag}"));
+        }
+        if let Some(push) = &self.git_push {
+            lines.push(format!("{GIT}Pushed:{GIT:#} {push}"));
+        }
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    /// Print what a dry run would change: the diff of every file, the config update, commit and tag
+    fn print_preview(&self) {
+        for file in self.files.values() {
+            for line in file.diff.lines() {
+                let style = match line {
+                    _ if line.starts_with("---") || line.starts_with("+++") => BOLD,
+                    _ if line.starts_with('+') => ADDED,
+                    _ if line.starts_with('-') => REMOVED,
+                    _ if line.starts_with("@@") => PATH,
+                    _ => Style::new(),
+                };
+                anstream::println!("{style}{line}{style:#}");
             }
         }
+        if let Some(config) = &self.config {
+            anstream::println!(
+                "{PATH}{}{PATH:#}: current_version {} -> {}",
+                config.path.display(),
+                config.current_version,
+                config.new_version
+            );
+        }
+        if let Some(message) = &self.git_commit_message {
+            anstream::println!("{GIT}Would commit:{GIT:#} {message}");
+        }
+        if let Some(tag) = &self.git_tag {
+            anstream::println!("{GIT}Would tag:{GIT:#} {tag}");
+        }
+        if let Some(push) = &self.git_push {
+            anstream::println!("{GIT}Would push:{GIT:#} {push}");
+        }
     }
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-#[command(
-    group(
-        ArgGroup::new("part")
-        .required(true)
-        .args(["major", "minor", "patch", "prerelease", "release", "new_version"]),
-    )
-)]
-#[command(
-    group(
-        ArgGroup::new("tag_group")
-        .required(false)
-        .multiple(false)
-        .args(["tag", "no_tag"])
-    )
-)]
-#[command(
-    group(
-        ArgGroup::new("commit_group")
-        .required(false)
-        .multiple(false)
-        .args(["commit", "no_commit"])
-    )
-)]
-struct Args {
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    /// Without a subcommand the bump flags can be used directly, e.g. `incrementor --minor`
+    #[command(flatten)]
+    bump: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Bump a part of the version and update all files
+    Bump(Args),
+    /// Print the current version, or the next version of a part
+    Show(ShowArgs),
+    /// Set the version and update all files
+    Set(SetArgs),
+    /// Create a new config file
+    Init(InitArgs),
+    /// Verify that all files contain the current version
+    Check,
+}
+
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
     /// Config file
-    #[arg(short = 'c', long)]
+    #[arg(short = 'c', long, global = true)]
     config: Option<String>,
 
-    /// Don't write any files, just pretend
-    #[arg(short = 'd', long)]
-    dry_run: bool,
+    /// Output format
+    #[arg(value_enum, short = 'o', long, default_value = "text", global = true)]
+    output: OutputFormat,
+
+    /// Tag name template, overrides `tag_name` of the config
+    #[arg(long, global = true)]
+    tag_name: Option<String>,
+
+    /// Environment variable with the file the github-output format appends to
+    #[arg(long, default_value = "GITHUB_OUTPUT", global = true)]
+    output_env: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[group(id = "part_group", required = true, multiple = false)]
+struct PartArgs {
+    /// Part to increment: major, minor, patch, release (remove prerelease), premajor, preminor,
+    /// prepatch or a part of the scheme
+    part: Option<String>,
 
     /// Increment prerelease
     #[arg(long)]
@@ -111,24 +239,69 @@ struct Args {
     #[arg(long)]
     release: bool,
 
-    /// Build metadata
+    /// Promote the prerelease to the next channel, e.g. alpha -> beta
     #[arg(long)]
-    build: Option<String>,
+    next_prerelease: bool,
+
+    /// Increment major and start a prerelease, the first channel when no label is given
+    #[arg(long, value_name = "LABEL")]
+    premajor: Option<Option<String>>,
+
+    /// Increment minor and start a prerelease, the first channel when no label is given
+    #[arg(long, value_name = "LABEL")]
+    preminor: Option<Option<String>>,
+
+    /// Increment patch and start a prerelease, the first channel when no label is given
+    #[arg(long, value_name = "LABEL")]
+    prepatch: Option<Option<String>>,
 
     /// Use supplied new version
     #[arg(long)]
     new_version: Option<String>,
 
-    /// Git Tag
+    /// Derive the part from the Conventional Commits since the last version tag
+    #[arg(long)]
+    auto: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct Args {
+    #[command(flatten)]
+    part: PartArgs,
+
+    /// Build metadata
+    #[arg(long)]
+    build: Option<String>,
+
+    /// Allow going back to an earlier prerelease channel
     #[arg(long)]
+    force: bool,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+/// Arguments of the commands that write files
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Don't write any files, just pretend
+    #[arg(short = 'd', long)]
+    dry_run: bool,
+
+    /// Git Tag
+    #[arg(long, conflicts_with = "no_tag")]
     tag: bool,
 
     /// Do not tag
     #[arg(long)]
     no_tag: bool,
 
-    /// Git commit
+    /// Replace the tag when it already exists
     #[arg(long)]
+    force_tag: bool,
+
+    /// Git commit
+    #[arg(long, conflicts_with = "no_commit")]
     commit: bool,
 
     /// Do not commit
@@ -139,6 +312,26 @@ struct Args {
     #[arg(long)]
     allow_dirty: bool,
 
+    /// Push the branch and/or tag after committing and tagging
+    #[arg(long, conflicts_with = "no_push")]
+    push: bool,
+
+    /// Do not push
+    #[arg(long)]
+    no_push: bool,
+
+    /// Sign the commit
+    #[arg(long)]
+    sign_commits: bool,
+
+    /// Sign the tag
+    #[arg(long)]
+    sign_tags: bool,
+
+    /// Key to sign with, the `user.signingkey` of git by default
+    #[arg(long, value_name = "KEY")]
+    signing_key: Option<String>,
+
     /// Git commit message
     #[arg(
         short = 'm',
@@ -146,13 +339,49 @@ struct Args {
         default_value = "bump {current_version} -> {new_version}"
     )]
     commit_message: String,
+}
 
-    #[arg(value_enum, short = 'o', long, default_value = "none")]
-    output: OutputFormat,
+#[derive(clap::Args, Debug)]
+struct ShowArgs {
+    /// Show the next version of this part instead of the current version
+    #[arg(long, conflicts_with = "prerelease")]
+    part: Option<String>,
+
+    /// Show the next prerelease version
+    #[arg(long)]
+    prerelease: Option<String>,
+
+    /// Build metadata
+    #[arg(long)]
+    build: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SetArgs {
+    /// The new version
+    version: String,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct InitArgs {
+    /// Initial version
+    #[arg(long, default_value = "0.1.0")]
+    current_version: String,
+
+    /// File containing the version, can be repeated
+    #[arg(long = "file")]
+    files: Vec<PathBuf>,
+
+    /// Overwrite an existing config file
+    #[arg(long)]
+    force: bool,
 }
 
 /// Parse the part (minor, major etc.) from the arguments
-fn parse_part_from_args(args: &Args) -> Part {
+fn parse_part_from_args(args: &PartArgs) -> Part {
     match (
         &args.prerelease,
         &args.patch,
@@ -165,360 +394,544 @@ fn parse_part_from_args(args: &Args) -> Part {
         (_, _, true, _, _) => Part::Minor,
         (_, _, _, true, _) => Part::Major,
         (_, _, _, _, true) => Part::Prerelease(None),
-        _ => Part::None,
+        _ if args.next_prerelease => Part::NextPrerelease,
+        _ if args.premajor.is_some() => Part::Premajor(args.premajor.clone().flatten()),
+        _ if args.preminor.is_some() => Part::Preminor(args.preminor.clone().flatten()),
+        _ if args.prepatch.is_some() => Part::Prepatch(args.prepatch.clone().flatten()),
+        _ => args.part.as_deref().map_or(Part::None, part_from_name),
+    }
+}
+
+/// Parse the part from its name, unknown names are parts of the scheme
+fn part_from_name(name: &str) -> Part {
+    match name {
+        "major" => Part::Major,
+        "minor" => Part::Minor,
+        "patch" => Part::Patch,
+        "release" => Part::Prerelease(None),
+        "premajor" => Part::Premajor(None),
+        "preminor" => Part::Preminor(None),
+        "prepatch" => Part::Prepatch(None),
+        _ => Part::Named(name.to_string()),
     }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let args: Args = Args::parse();
+    let cli = Cli::parse();
 
-    // Parse config from config path, or default to ./incrementor.toml
-    let (mut config, config_path) = if let Some(ref path) = args.config {
-        (Config::from(Toml::file(path.clone()))?, path.clone())
-    } else {
-        (
-            Config::from(Config::figment())?,
-            WORKDIR_CONFIG_PATH.to_string(),
-        )
-    };
+    match cli.command {
+        Some(Command::Bump(args)) => run_bump(&cli.global, args),
+        Some(Command::Show(args)) => run_show(&cli.global, args),
+        Some(Command::Set(args)) => run_set(&cli.global, args),
+        Some(Command::Init(args)) => run_init(&cli.global, args),
+        Some(Command::Check) => run_check(&cli.global),
+        None => run_bump(&cli.global, cli.bump),
+    }
+}
 
-    // Setup git related things
-    let git_tag = (args.tag && !args.no_tag) || config.tag;
-    let git_commit = (args.commit && !args.no_commit) || config.commit;
-    let git_commit_message = config
-        .commit_message
-        .clone()
-        .unwrap_or(args.commit_message.clone());
-    let git = Git::new(args.allow_dirty)?;
-    if (git_tag || git_commit) && git.is_dirty() {
-        return Err(eyre!("Repository is dirty"));
+/// Parse config from config path, or default to ./incrementor.toml
+fn load_config(global: &GlobalArgs) -> Result<Bumper> {
+    let path = global.config.as_deref().map(Path::new);
+    let mut figment = match path {
+        Some(path) => Config::file(path),
+        None => Config::figment(),
+    };
+    if let Some(tag_name) = &global.tag_name {
+        figment = figment.merge(Serialized::default("tag_name", tag_name));
     }
+    let config_path = path.unwrap_or(Path::new(WORKDIR_CONFIG_PATH));
+    Ok(Bumper::from_provider(figment, config_path)?)
+}
 
-    // Parse part from arguments
-    let part = parse_part_from_args(&args);
+fn run_bump(global: &GlobalArgs, args: Args) -> Result<()> {
+    let bumper = load_config(global)?;
 
-    // Create or use the new_version
-    let current_version = config.current_version.clone();
-    let maybe_new_version = args
-        .new_version
-        .map(|s| Version::parse(&s).expect("Invalid new_version"));
-    let new_version = if let Some(version) = maybe_new_version {
-        version
-    } else {
-        bump(&current_version, &part, args.build.clone())?
+    // Parse part from arguments, or derive it from the commits
+    let auto = match args.part.auto {
+        true => Some(bumper.auto_bump()?),
+        false => None,
     };
+    let part = auto
+        .as_ref()
+        .map_or_else(|| parse_part_from_args(&args.part), AutoBump::part);
 
-    // Setup placeholders
-    let placeholders = Placeholders {
-        current_version: &current_version,
-        new_version: &new_version,
+    // Create or use the new_version
+    let new_version = match args.part.new_version {
+        Some(ref s) => bumper
+            .parse_version(s)
+            .wrap_err(format!("Invalid new_version '{s}'"))?,
+        None => bumper.next_version(&part, args.build.clone(), args.force)?,
     };
 
-    // Setup output buffer
-    let mut output = Output {
-        dry_run: args.dry_run,
-        build_metadata: args.build.clone(),
-        part: &part,
-        current_version: &current_version,
-        new_version: &new_version,
-        files: HashMap::new(),
-        git_tag: None,
-        git_commit_message: None,
+    apply(
+        global,
+        &args.run,
+        &bumper,
+        part,
+        args.build,
+        new_version,
+        auto.as_ref(),
+    )
+}
+
+fn run_set(global: &GlobalArgs, args: SetArgs) -> Result<()> {
+    let bumper = load_config(global)?;
+    let new_version = bumper
+        .parse_version(&args.version)
+        .wrap_err(format!("Invalid version '{}'", args.version))?;
+    apply(
+        global,
+        &args.run,
+        &bumper,
+        Part::None,
+        None,
+        new_version,
+        None,
+    )
+}
+
+fn run_show(global: &GlobalArgs, args: ShowArgs) -> Result<()> {
+    let bumper = load_config(global)?;
+    let current_version = bumper.current_version()?;
+
+    let part = match (args.part, args.prerelease) {
+        (Some(part), _) => part_from_name(&part),
+        (None, Some(label)) => Part::Prerelease(Some(label)),
+        (None, None) => Part::None,
+    };
+    let version = match part {
+        Part::None => current_version.clone(),
+        _ => bumper.next_version(&part, args.build, false)?,
     };
 
-    for (file_path, file_config) in config.files.iter() {
-        let content = fs::read_to_string(file_path)
-            .context(format!("File {} not found", file_path.to_str().unwrap()))?;
-
-        match replace_version(content, file_path, file_config, &placeholders) {
-            Ok(result) => {
-                output.files.insert(
-                    file_path.to_str().unwrap(),
-                    FileOutput {
-                        contents: result.clone(),
-                    },
-                );
-                if !args.dry_run {
-                    fs::write(file_path, result)?
-                }
-            }
-            Err(err) => {
-                return Err(err);
-            }
+    match global.output {
+        OutputFormat::Json => {
+            let json = serde_json::json!({
+                "current_version": current_version,
+                "part": part,
+                "version": version,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
         }
+        OutputFormat::Text => println!("{version}"),
+        OutputFormat::None => {}
+        _ => print_versions(global, &current_version, &version)?,
     }
 
-    // Finalize and write the config with the `new_version` as `current_version`
-    config.current_version = new_version.clone();
-    if !args.dry_run {
-        let content = toml::to_string_pretty(&config)?;
-        fs::write(config_path, content)?;
-    }
+    Ok(())
+}
 
-    if git_commit && !args.dry_run {
-        let message = placeholders.replace(&git_commit_message);
-        git.commit(&message)?;
-        output.git_commit_message = Some(message);
+fn run_init(global: &GlobalArgs, args: InitArgs) -> Result<()> {
+    let path = PathBuf::from(global.config.as_deref().unwrap_or(WORKDIR_CONFIG_PATH));
+    if path.exists() && !args.force {
+        return Err(eyre!(
+            "Config file {} already exists, use --force to overwrite",
+            path.display()
+        ));
     }
 
-    if git_tag && !args.dry_run {
-        let tag = format!("v{new_version}");
-        git.tag(&tag, &tag)?;
-        output.git_tag = Some(tag);
-    }
+    let version = Version::parse(&args.current_version)
+        .wrap_err(format!("Invalid version '{}'", args.current_version))?;
+    fs::write(&path, init_config(&version, &args.files))?;
 
-    output.print(args.output);
+    match global.output {
+        OutputFormat::Json => {
+            let json = serde_json::json!({ "config": path, "current_version": version });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        OutputFormat::Text => {
+            anstream::println!("Created {PATH}{}{PATH:#} at {version}", path.display())
+        }
+        _ => {}
+    }
 
     Ok(())
 }
 
-fn replace_version(
-    content: String,
-    file_path: &PathBuf,
-    file_config: &FileConfig,
-    placeholders: &Placeholders,
-) -> Result<String> {
-    let search_re = RegexBuilder::new(
-        &placeholders
-            .replace(&file_config.search)
-            // Escape + sign
-            .replace('+', "\\+"),
-    )
-    .multi_line(true)
-    .build()?;
+/// Contents of a new config file
+fn init_config(version: &Version, files: &[PathBuf]) -> String {
+    let mut doc = DocumentMut::new();
+    doc["current_version"] = toml_edit::value(version.to_string());
+    doc["commit"] = toml_edit::value(false);
+    doc["tag"] = toml_edit::value(false);
+
+    let defaults = FileConfig::default();
+    let mut table = Table::new();
+    table.set_implicit(true);
+    for file in files {
+        let mut file_table = Table::new();
+        file_table["search"] = toml_edit::value(&defaults.search);
+        file_table["replace"] = toml_edit::value(&defaults.replace);
+        table[&file.to_string_lossy()] = Item::Table(file_table);
+    }
+    doc["files"] = Item::Table(table);
+
+    doc.to_string()
+}
 
-    let replace_value = placeholders.replace(&file_config.replace);
+fn run_check(global: &GlobalArgs) -> Result<()> {
+    let bumper = load_config(global)?;
+    let config = bumper.config();
+    // Files are searched for the version as written in the config, but it must be valid
+    config.version()?;
+
+    let results: Vec<FileCheck> = config
+        .files
+        .iter()
+        .map(|(file_path, file_config)| check_file(file_path, file_config, &config.current_version))
+        .collect();
+    let drifted = results.iter().filter(|result| !result.ok).count();
+
+    match global.output {
+        OutputFormat::Json => {
+            let json = serde_json::json!({
+                "current_version": config.current_version,
+                "files": results,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        _ => {
+            for result in results.iter() {
+                println!("{result}");
+            }
+        }
+    }
 
-    if search_re.is_match(&content) {
-        Ok(search_re.replace_all(&content, replace_value).to_string())
+    if drifted == 0 {
+        Ok(())
     } else {
         Err(eyre!(
-            "Unable to find current version ({:?}) in {:?}",
-            search_re,
-            file_path
+            "{drifted} of {} file(s) do not contain the current version {}",
+            results.len(),
+            config.current_version
         ))
     }
 }
 
+/// Write the new version to all files and the config, then commit and tag
+fn apply(
+    global: &GlobalArgs,
+    args: &RunArgs,
+    bumper: &Bumper,
+    part: Part,
+    build: Option<String>,
+    new_version: AnyVersion,
+    auto: Option<&AutoBump>,
+) -> Result<()> {
+    let config = bumper.config();
+
+    // Setup git related things
+    let git = GitOptions {
+        tag: (args.tag && !args.no_tag) || config.tag,
+        commit: (args.commit && !args.no_commit) || config.commit,
+        commit_message: config
+            .commit_message
+            .clone()
+            .unwrap_or(args.commit_message.clone()),
+        allow_dirty: args.allow_dirty,
+        force_tag: args.force_tag,
+        signing: Signing {
+            commits: args.sign_commits || config.sign_commits,
+            tags: args.sign_tags || config.sign_tags,
+            key: args.signing_key.clone().or(config.signing_key.clone()),
+        },
+        push: ((args.push || config.push.enabled) && !args.no_push).then(|| config.push.clone()),
+    };
+
+    // Compute all changes before writing anything
+    let plan = bumper.plan(new_version, &git)?;
+
+    let mut output = Output {
+        dry_run: args.dry_run,
+        build_metadata: build,
+        part: &part,
+        auto,
+        current_version: &plan.current_version,
+        new_version: &plan.new_version,
+        files: IndexMap::new(),
+        config: plan.config_path.as_deref().map(|path| ConfigOutput {
+            path,
+            current_version: &plan.current_version,
+            new_version: &plan.new_version,
+        }),
+        git_tag: plan.tag.clone(),
+        git_commit_message: plan.commit_message.clone(),
+        git_push: plan.push.as_ref().map(|push| push.to_string()),
+    };
+    // The config update is reported on its own, unless the config is one of the files
+    for change in plan.changes.iter().filter(|change| {
+        config.files.contains_key(&change.path) || plan.config_path.as_ref() != Some(&change.path)
+    }) {
+        output.files.insert(
+            change.path.to_str().unwrap(),
+            FileOutput {
+                contents: (!args.dry_run).then(|| change.contents.clone()),
+                replacements: change.replacements,
+                diff: change.diff(),
+            },
+        );
+    }
+
+    if !args.dry_run {
+        plan.apply()?;
+    }
+
+    output.print(global)
+}
+
+/// Explain which commits the part was derived from
+fn print_auto(auto: &AutoBump) {
+    let since = auto
+        .since
+        .as_ref()
+        .map_or("in the history".to_string(), |tag| format!("since {tag}"));
+    anstream::println!(
+        "{BOLD}{}{BOLD:#} release, {} commit(s) {since}:",
+        auto.level,
+        auto.commits.len()
+    );
+    for commit in auto.drivers() {
+        anstream::println!("  {GIT}{commit}{GIT:#}");
+    }
+}
+
+/// Variables of the current and new version, for the shell friendly output formats
+fn version_variables(
+    current_version: &AnyVersion,
+    new_version: &AnyVersion,
+) -> Vec<(String, String)> {
+    let mut variables = vec![
+        ("current_version".to_string(), current_version.to_string()),
+        ("new_version".to_string(), new_version.to_string()),
+    ];
+    for (name, value) in new_version.parts() {
+        variables.push((format!("new_{name}"), value));
+    }
+    variables
+}
+
+/// Print the versions in one of the shell friendly formats (`version`, `dotenv` and `github-output`)
+fn print_versions(
+    global: &GlobalArgs,
+    current_version: &AnyVersion,
+    new_version: &AnyVersion,
+) -> Result<()> {
+    let variables = version_variables(current_version, new_version);
+    match global.output {
+        OutputFormat::Version => println!("{new_version}"),
+        OutputFormat::Dotenv => print!("{}", format_dotenv(&variables)),
+        OutputFormat::GithubOutput => {
+            let path = std::env::var_os(&global.output_env).ok_or_else(|| {
+                eyre!(
+                    "${} is not set, it should name the file to append the outputs to",
+                    global.output_env
+                )
+            })?;
+            append_outputs(Path::new(&path), &variables)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `KEY=value` lines with upper case keys
+fn format_dotenv(variables: &[(String, String)]) -> String {
+    variables
+        .iter()
+        .map(|(key, value)| format!("{}={value}\n", key.to_uppercase()))
+        .collect()
+}
+
+/// Append `key=value` lines to a GitHub Actions style output file
+fn append_outputs(path: &Path, variables: &[(String, String)]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .wrap_err(format!("Unable to open {}", path.display()))?;
+    for (key, value) in variables {
+        writeln!(file, "{key}={value}")?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{parse_part_from_args, replace_version, Args, FileConfig, OutputFormat};
-    use incrementor::{Part, Placeholders};
+    use crate::{
+        append_outputs, format_dotenv, init_config, parse_part_from_args, version_variables, Cli,
+        Command, ConfigOutput, FileOutput, Output, SetArgs, ShowArgs,
+    };
+    use clap::Parser;
+    use figment::providers::{Format, Toml};
+    use incrementor::config::Config;
+    use incrementor::{Part, Scheme};
+    use indexmap::IndexMap;
     use semver::Version;
-    use std::path::Path;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
 
     #[test]
     fn test_parse_part() {
-        struct TestCase {
-            args: Args,
-            part: Part,
-        }
-
         let cases = vec![
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: None,
-                    patch: false,
-                    minor: false,
-                    major: false,
-                    release: false,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::None,
-            },
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: None,
-                    patch: false,
-                    minor: false,
-                    major: true,
-                    release: false,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::Major,
-            },
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: None,
-                    patch: false,
-                    minor: true,
-                    major: false,
-                    release: false,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::Minor,
-            },
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: None,
-                    patch: true,
-                    minor: false,
-                    major: false,
-                    release: false,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::Patch,
-            },
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: Some("beta".to_string()),
-                    patch: false,
-                    minor: false,
-                    major: false,
-                    release: false,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::Prerelease(Some("beta".to_string())),
-            },
-            TestCase {
-                args: Args {
-                    config: None,
-                    dry_run: false,
-                    prerelease: None,
-                    patch: false,
-                    minor: false,
-                    major: false,
-                    release: true,
-                    build: None,
-                    new_version: None,
-                    tag: false,
-                    no_tag: false,
-                    commit: false,
-                    no_commit: false,
-                    allow_dirty: false,
-                    commit_message: "".to_string(),
-                    output: OutputFormat::Json,
-                },
-                part: Part::Prerelease(None),
-            },
+            (vec!["--major"], Part::Major),
+            (vec!["--minor"], Part::Minor),
+            (vec!["--patch"], Part::Patch),
+            (
+                vec!["--prerelease", "beta"],
+                Part::Prerelease(Some("beta".to_string())),
+            ),
+            (vec!["--release"], Part::Prerelease(None)),
+            (vec!["--next-prerelease"], Part::NextPrerelease),
+            (
+                vec!["--preminor", "beta"],
+                Part::Preminor(Some("beta".to_string())),
+            ),
+            (vec!["--premajor"], Part::Premajor(None)),
+            (vec!["bump", "prepatch"], Part::Prepatch(None)),
+            (vec!["bump", "major"], Part::Major),
+            (vec!["bump", "minor"], Part::Minor),
+            (vec!["bump", "patch"], Part::Patch),
+            (vec!["bump", "release"], Part::Prerelease(None)),
+            (vec!["bump", "build"], Part::Named("build".to_string())),
+            (
+                vec!["bump", "--prerelease", "rc"],
+                Part::Prerelease(Some("rc".to_string())),
+            ),
         ];
 
-        for case in cases {
-            let part = parse_part_from_args(&case.args);
-            assert_eq!(part, case.part)
+        for (args, part) in cases {
+            let cli = Cli::parse_from(["incrementor"].into_iter().chain(args));
+            let args = match cli.command {
+                Some(Command::Bump(args)) => args,
+                _ => cli.bump,
+            };
+            assert_eq!(parse_part_from_args(&args.part), part)
         }
     }
 
     #[test]
-    fn test_replace() {
-        let placeholders = Placeholders {
-            current_version: &Version::new(0, 1, 0),
-            new_version: &Version::new(0, 2, 0),
-        };
-
-        let content = r#"
-            version = "0.1.0"
-
-            [dependencies]
-            some-dep = { version = "0.1.0" }
-        "#;
-
-        let file_config = FileConfig {
-            search: "version = \"{current_version}\"$".to_string(),
-            replace: "version = \"{new_version}\"".to_string(),
-        };
-
-        let file_path = Path::new("Cargo.toml");
-
-        let res = replace_version(
-            content.to_string(),
-            &file_path.to_path_buf(),
-            &file_config,
-            &placeholders,
-        )
-        .unwrap();
+    fn test_parse_subcommands() {
+        // Part is required when bumping
+        assert!(Cli::try_parse_from(["incrementor"]).is_err());
+        assert!(Cli::try_parse_from(["incrementor", "bump"]).is_err());
+        assert!(Cli::try_parse_from(["incrementor", "--major", "--minor"]).is_err());
+        assert!(Cli::try_parse_from(["incrementor", "--auto", "--minor"]).is_err());
+        assert!(Cli::parse_from(["incrementor", "--auto"]).bump.part.auto);
+        let cli = Cli::parse_from(["incrementor", "--minor", "--tag-name", "app-{new_version}"]);
+        assert_eq!(cli.global.tag_name.as_deref(), Some("app-{new_version}"));
+
+        let cli = Cli::parse_from(["incrementor", "show", "--part", "minor", "-c", "x.toml"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Show(ShowArgs {
+                part: Some(part),
+                ..
+            })) if part == "minor"
+        ));
+        assert_eq!(cli.global.config.as_deref(), Some("x.toml"));
+
+        let cli = Cli::parse_from(["incrementor", "set", "1.2.3", "--dry-run"]);
+        assert!(
+            matches!(cli.command, Some(Command::Set(SetArgs { version, run })) if version == "1.2.3" && run.dry_run)
+        );
 
-        assert_eq!(
-            res,
-            r#"
-            version = "0.2.0"
+        let cli = Cli::parse_from(["incrementor", "check"]);
+        assert!(matches!(cli.command, Some(Command::Check)));
+    }
 
-            [dependencies]
-            some-dep = { version = "0.1.0" }
-        "#
+    #[test]
+    fn test_init_config() {
+        let content = init_config(
+            &Version::new(1, 0, 0),
+            &[PathBuf::from("VERSION"), PathBuf::from("Cargo.toml")],
         );
+        let config = Config::from(Toml::string(&content)).unwrap();
+        assert_eq!(config.current_version, "1.0.0");
+        assert!(config.files.contains_key(Path::new("VERSION")));
+        assert!(config.files.contains_key(Path::new("Cargo.toml")));
     }
 
     #[test]
-    fn test_failure() {
-        let placeholders = Placeholders {
-            current_version: &Version::new(0, 1, 0),
-            new_version: &Version::new(0, 2, 0),
+    fn test_format_text() {
+        let current_version = Scheme::Semver.parse("1.2.3").unwrap();
+        let new_version = Scheme::Semver.parse("1.3.0").unwrap();
+        let file = |replacements| FileOutput {
+            contents: None,
+            replacements,
+            diff: String::new(),
+        };
+        let output = Output {
+            dry_run: false,
+            part: &Part::Minor,
+            auto: None,
+            build_metadata: None,
+            current_version: &current_version,
+            new_version: &new_version,
+            files: IndexMap::from([
+                ("VERSION", file(1)),
+                ("Cargo.toml", file(2)),
+                ("CHANGELOG.md", file(0)),
+            ]),
+            config: Some(ConfigOutput {
+                path: Path::new("incrementor.toml"),
+                current_version: &current_version,
+                new_version: &new_version,
+            }),
+            git_tag: Some("v1.3.0".to_string()),
+            git_commit_message: Some("Bump 1.2.3 → 1.3.0".to_string()),
+            git_push: Some("origin HEAD refs/tags/v1.3.0".to_string()),
         };
 
-        let content = r#"
-            version = "0.2.0"
-
-            [dependencies]
-            some-dep = { version = "0.1.0" }
-        "#;
+        let text = output.format_text();
+        assert_eq!(
+            anstream::adapter::strip_str(&text).to_string(),
+            "Bumped 1.2.3 → 1.3.0\n\
+             \x20 VERSION: 1 replacement\n\
+             \x20 Cargo.toml: 2 replacements\n\
+             \x20 CHANGELOG.md: updated\n\
+             \x20 incrementor.toml: current_version updated\n\
+             Committed: Bump 1.2.3 → 1.3.0\n\
+             Tagged: v1.3.0\n\
+             Pushed: origin HEAD refs/tags/v1.3.0\n"
+        );
 
-        let file_config = FileConfig {
-            search: "version = \"{current_version}\"$".to_string(),
-            replace: "version = \"{new_version}\"".to_string(),
+        // Without git and the config only the version and files are listed
+        let output = Output {
+            config: None,
+            git_tag: None,
+            git_commit_message: None,
+            git_push: None,
+            files: IndexMap::new(),
+            ..output
         };
+        assert_eq!(
+            anstream::adapter::strip_str(&output.format_text()).to_string(),
+            "Bumped 1.2.3 → 1.3.0\n"
+        );
+    }
 
-        let file_path = Path::new("Cargo.toml");
+    #[test]
+    fn test_output_variables() {
+        let current_version = Scheme::Semver.parse("1.2.3").unwrap();
+        let new_version = Scheme::Semver.parse("1.3.0-beta.1").unwrap();
+        let variables = version_variables(&current_version, &new_version);
 
-        let res = replace_version(
-            content.to_string(),
-            &file_path.to_path_buf(),
-            &file_config,
-            &placeholders,
+        assert_eq!(
+            format_dotenv(&variables),
+            "CURRENT_VERSION=1.2.3\nNEW_VERSION=1.3.0-beta.1\nNEW_MAJOR=1\nNEW_MINOR=3\nNEW_PATCH=0\n\
+             NEW_PRERELEASE=beta.1\nNEW_BUILD=\n"
         );
 
-        assert!(res.is_err());
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("github_output");
+        fs::write(&path, "other=value\n").unwrap();
+        append_outputs(&path, &variables[..2]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "other=value\ncurrent_version=1.2.3\nnew_version=1.3.0-beta.1\n"
+        );
     }
 }
@@ -0,0 +1,559 @@
+//! Python package versions, see <https://peps.python.org/pep-0440/>
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use regex::RegexBuilder;
+
+use crate::{increment, Error, Part, Result};
+
+/// Version pattern from the specification, accepting all non-normalized forms
+const PATTERN: &str = r"
+    ^\s*v?
+    (?:(?P<epoch>[0-9]+)!)?
+    (?P<release>[0-9]+(?:\.[0-9]+)*)
+    (?P<pre>
+        [-_\.]?
+        (?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)
+        [-_\.]?
+        (?P<pre_n>[0-9]+)?
+    )?
+    (?P<post>
+        (?:-(?P<post_n1>[0-9]+))
+        |
+        (?:
+            [-_\.]?
+            (?P<post_l>post|rev|r)
+            [-_\.]?
+            (?P<post_n2>[0-9]+)?
+        )
+    )?
+    (?P<dev>
+        [-_\.]?
+        (?P<dev_l>dev)
+        [-_\.]?
+        (?P<dev_n>[0-9]+)?
+    )?
+    (?:\+(?P<local>[a-z0-9]+(?:[-_\.][a-z0-9]+)*))?
+    \s*$";
+
+/// Kind of a pre-release, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreKind {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+impl FromStr for PreKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "a" | "alpha" => Ok(PreKind::Alpha),
+            "b" | "beta" => Ok(PreKind::Beta),
+            "rc" | "c" | "pre" | "preview" => Ok(PreKind::Rc),
+            _ => Err(Error::InvalidPrerelease {
+                prerelease: s.to_string(),
+                reason: "expected one of 'a', 'b' or 'rc'".to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for PreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PreKind::Alpha => "a",
+            PreKind::Beta => "b",
+            PreKind::Rc => "rc",
+        })
+    }
+}
+
+/// Segment of a local version label, numbers sort after strings
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum LocalSegment {
+    String(String),
+    Number(u64),
+}
+
+/// A PEP 440 version, e.g. `1.4.0rc2`, `1.4.0.post1` or `1.4.0.dev3`
+#[derive(Debug, Clone)]
+pub struct Pep440Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreKind, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    /// Normalized local version label, e.g. `ubuntu.1`
+    pub local: Option<String>,
+}
+
+impl FromStr for Pep440Version {
+    type Err = Error;
+
+    fn from_str(version: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidVersion {
+            version: version.to_string(),
+            reason,
+        };
+        let re = RegexBuilder::new(PATTERN)
+            .case_insensitive(true)
+            .ignore_whitespace(true)
+            .build()
+            .expect("valid PEP 440 pattern");
+        let captures = re
+            .captures(version)
+            .ok_or_else(|| invalid("not a PEP 440 version".to_string()))?;
+
+        let number = |name: &str| -> Result<Option<u64>> {
+            captures
+                .name(name)
+                .map(|m| m.as_str().parse::<u64>())
+                .transpose()
+                .map_err(|err| invalid(format!("invalid number: {err}")))
+        };
+
+        let release = captures["release"]
+            .split('.')
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|err| invalid(format!("invalid number: {err}")))?;
+
+        let pre = match captures.name("pre_l") {
+            Some(label) => Some((label.as_str().parse()?, number("pre_n")?.unwrap_or(0))),
+            None => None,
+        };
+        let post = match captures.name("post") {
+            Some(_) => Some(number("post_n1")?.or(number("post_n2")?).unwrap_or(0)),
+            None => None,
+        };
+        let dev = match captures.name("dev") {
+            Some(_) => Some(number("dev_n")?.unwrap_or(0)),
+            None => None,
+        };
+        let local = captures
+            .name("local")
+            .map(|m| m.as_str().to_lowercase().replace(['-', '_'], "."));
+
+        Ok(Pep440Version {
+            epoch: number("epoch")?.unwrap_or(0),
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+impl Pep440Version {
+    pub fn is_final(&self) -> bool {
+        self.pre.is_none() && self.dev.is_none()
+    }
+
+    /// Increment a release segment (0 is major), zeroing the segments after it
+    pub fn bump_release(&self, index: usize) -> Result<Pep440Version> {
+        let part = match index {
+            0 => Part::Major,
+            1 => Part::Minor,
+            2 => Part::Patch,
+            _ => Part::Named(format!("release segment {index}")),
+        };
+        let mut release = self.release.clone();
+        if release.len() <= index {
+            release.resize(index + 1, 0);
+        }
+        release[index] = increment(release[index], &part, self)?;
+        release[index + 1..].iter_mut().for_each(|n| *n = 0);
+
+        Ok(Pep440Version {
+            epoch: self.epoch,
+            release,
+            pre: None,
+            post: None,
+            dev: None,
+            local: None,
+        })
+    }
+
+    /// Increment the last release segment, e.g. the patch of `1.4.0`
+    fn bump_last_release(&self) -> Result<Pep440Version> {
+        self.bump_release(self.release.len().max(1) - 1)
+    }
+
+    /// Increment or start a pre-release, going back to an earlier kind requires `force`.
+    ///
+    /// A pre-release sorts before its release, so the last release segment of a final or
+    /// post-release is incremented first, e.g. `1.4.0` -> `1.4.1rc1`.
+    pub fn bump_pre(&self, kind: PreKind, force: bool) -> Result<Pep440Version> {
+        let part = Part::Prerelease(Some(kind.to_string()));
+        let (release, n) = match self.pre {
+            Some((current, n)) if current == kind => (self.clone(), increment(n, &part, self)?),
+            Some((current, _)) if current > kind && !force => {
+                return Err(Error::unsupported_part(
+                    &part,
+                    self,
+                    format!("can't go back from pre-release '{current}' to '{kind}'"),
+                ))
+            }
+            Some(_) => (self.clone(), 1),
+            // The development release of a release sorts before its pre-releases
+            None if self.dev.is_some() && self.post.is_none() => (self.clone(), 1),
+            None => (self.bump_last_release()?, 1),
+        };
+        Ok(release.with_pre(kind, n))
+    }
+
+    /// The pre-release `n` of the same release
+    pub(crate) fn with_pre(&self, kind: PreKind, n: u64) -> Pep440Version {
+        Pep440Version {
+            pre: Some((kind, n)),
+            post: None,
+            dev: None,
+            local: None,
+            ..self.clone()
+        }
+    }
+
+    /// Promote the pre-release to the next kind, e.g. `a3` -> `b1`
+    pub fn next_pre(&self) -> Result<Pep440Version> {
+        let kind = match self.pre {
+            Some((PreKind::Alpha, _)) => PreKind::Beta,
+            Some((PreKind::Beta, _)) => PreKind::Rc,
+            Some((PreKind::Rc, _)) => {
+                return Err(Error::unsupported_part(
+                    &Part::NextPrerelease,
+                    self,
+                    "it is a release candidate, use release to remove it",
+                ))
+            }
+            None => {
+                return Err(Error::unsupported_part(
+                    &Part::NextPrerelease,
+                    self,
+                    "it has no pre-release to promote",
+                ))
+            }
+        };
+        self.bump_pre(kind, false)
+    }
+
+    /// Increment or start a post-release
+    pub fn bump_post(&self) -> Result<Pep440Version> {
+        let part = Part::Named("post".to_string());
+        Ok(Pep440Version {
+            post: Some(self.post.map_or(Ok(1), |n| increment(n, &part, self))?),
+            dev: None,
+            local: None,
+            ..self.clone()
+        })
+    }
+
+    /// Increment the development release, or start one for the next version.
+    ///
+    /// A `.dev` release sorts before the version it is added to, so without one the
+    /// post-release, pre-release or last release segment is incremented first, e.g.
+    /// `1.4.0` -> `1.4.1.dev1` and `1.4.0rc1` -> `1.4.0rc2.dev1`.
+    pub fn bump_dev(&self) -> Result<Pep440Version> {
+        let part = Part::Named("dev".to_string());
+        let next = match (self.dev, self.post, self.pre) {
+            (Some(_), _, _) => self.clone(),
+            (None, Some(post), _) => Pep440Version {
+                post: Some(increment(post, &part, self)?),
+                ..self.clone()
+            },
+            (None, None, Some((kind, n))) => Pep440Version {
+                pre: Some((kind, increment(n, &part, self)?)),
+                ..self.clone()
+            },
+            (None, None, None) => self.bump_last_release()?,
+        };
+        Ok(Pep440Version {
+            dev: Some(self.dev.map_or(Ok(1), |n| increment(n, &part, self))?),
+            local: None,
+            ..next
+        })
+    }
+
+    /// Remove the pre-release and development release, a post-release without a
+    /// pre-release is kept as removing it would go back
+    pub fn finalize(&self) -> Result<Pep440Version> {
+        if self.is_final() {
+            return Err(Error::NoOpBump {
+                part: Part::Prerelease(None),
+                version: self.to_string(),
+                reason: "it is already a final release".to_string(),
+            });
+        }
+        Ok(Pep440Version {
+            pre: None,
+            post: self.pre.is_none().then_some(self.post).flatten(),
+            dev: None,
+            local: None,
+            ..self.clone()
+        })
+    }
+
+    /// Sort key, following the ordering of the specification
+    #[allow(clippy::type_complexity)]
+    fn key(
+        &self,
+    ) -> (
+        u64,
+        &[u64],
+        (u8, Option<(PreKind, u64)>),
+        Option<u64>,
+        (bool, Option<u64>),
+        Option<Vec<LocalSegment>>,
+    ) {
+        // Trailing zeros don't matter, 1.0 == 1.0.0
+        let len = self
+            .release
+            .iter()
+            .rposition(|n| *n != 0)
+            .map_or(0, |i| i + 1);
+
+        let pre = match (self.pre, self.post, self.dev) {
+            // A development release of a final release sorts before its pre-releases
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre)),
+            (None, _, _) => (2, None),
+        };
+        let local = self.local.as_ref().map(|local| {
+            local
+                .split('.')
+                .map(|s| match s.parse() {
+                    Ok(n) => LocalSegment::Number(n),
+                    Err(_) => LocalSegment::String(s.to_string()),
+                })
+                .collect()
+        });
+
+        (
+            self.epoch,
+            &self.release[..len],
+            pre,
+            self.post,
+            // No development release sorts after all development releases
+            (self.dev.is_none(), self.dev),
+            local,
+        )
+    }
+}
+
+impl PartialEq for Pep440Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Pep440Version {}
+
+impl Ord for Pep440Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for Pep440Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Writes the normalized form
+impl Display for Pep440Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(|n| n.to_string()).collect();
+        f.write_str(&release.join("."))?;
+        if let Some((kind, n)) = self.pre {
+            write!(f, "{kind}{n}")?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{n}")?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{n}")?;
+        }
+        if let Some(local) = &self.local {
+            write!(f, "+{local}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Pep440Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("1.4.0rc2", "1.4.0rc2"),
+            ("1.4.0RC2", "1.4.0rc2"),
+            ("1.4.0-rc.2", "1.4.0rc2"),
+            ("1.4.0c2", "1.4.0rc2"),
+            ("1.4.0.alpha", "1.4.0a0"),
+            ("1.4.0-beta-1", "1.4.0b1"),
+            ("1.4.0.post1", "1.4.0.post1"),
+            ("1.4.0-1", "1.4.0.post1"),
+            ("1.4.0rev", "1.4.0.post0"),
+            ("1.4.0.dev3", "1.4.0.dev3"),
+            ("1.4.0-DEV", "1.4.0.dev0"),
+            ("v1!2.0+Ubuntu-1", "1!2.0+ubuntu.1"),
+        ];
+        for (input, expect) in cases {
+            assert_eq!(v(input).to_string(), expect);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for input in ["", "1.4.0-foo", "one", "1.4.0+", "1..0"] {
+            assert!(input.parse::<Pep440Version>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        let ordered = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.1.dev1",
+            "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(v("1.0"), v("1.0.0"));
+    }
+
+    #[test]
+    fn test_bump() {
+        assert_eq!(v("1.4.0rc2").bump_release(0).unwrap().to_string(), "2.0.0");
+        assert_eq!(
+            v("1.4.0.post1").bump_release(1).unwrap().to_string(),
+            "1.5.0"
+        );
+        assert_eq!(v("1.4").bump_release(2).unwrap().to_string(), "1.4.1");
+        assert_eq!(
+            v("1.4.0").bump_pre(PreKind::Rc, false).unwrap().to_string(),
+            "1.4.1rc1"
+        );
+        assert_eq!(
+            v("1.4.0rc1")
+                .bump_pre(PreKind::Rc, false)
+                .unwrap()
+                .to_string(),
+            "1.4.0rc2"
+        );
+        assert_eq!(
+            v("1.4.0a3")
+                .bump_pre(PreKind::Beta, false)
+                .unwrap()
+                .to_string(),
+            "1.4.0b1"
+        );
+        assert!(v("1.4.0rc1").bump_pre(PreKind::Alpha, false).is_err());
+        assert_eq!(
+            v("1.4.0rc1")
+                .bump_pre(PreKind::Alpha, true)
+                .unwrap()
+                .to_string(),
+            "1.4.0a1"
+        );
+        assert_eq!(v("1.4.0a3").next_pre().unwrap().to_string(), "1.4.0b1");
+        assert!(v("1.4.0rc1").next_pre().is_err());
+        assert!(v("1.4.0").next_pre().is_err());
+        assert_eq!(v("1.4.0").bump_post().unwrap().to_string(), "1.4.0.post1");
+        assert_eq!(
+            v("1.4.0.post1.dev2").bump_post().unwrap().to_string(),
+            "1.4.0.post2"
+        );
+        assert_eq!(
+            v("1.4.0a1.dev3").bump_dev().unwrap().to_string(),
+            "1.4.0a1.dev4"
+        );
+        assert_eq!(v("1.4.0rc2.dev1").finalize().unwrap().to_string(), "1.4.0");
+        assert!(v("1.4.0").finalize().is_err());
+        assert_eq!(
+            v("1.4.0.post1.dev2").finalize().unwrap().to_string(),
+            "1.4.0.post1"
+        );
+        assert_eq!(
+            v("1.4.0rc1.post1.dev2").finalize().unwrap().to_string(),
+            "1.4.0"
+        );
+        assert!(v("1.4.0.post1").finalize().is_err());
+
+        // Numbers at the highest value can't be incremented
+        let max = u64::MAX;
+        assert!(v(&format!("1.{max}")).bump_release(1).is_err());
+        assert!(v(&format!("1.4.0rc{max}"))
+            .bump_pre(PreKind::Rc, false)
+            .is_err());
+        assert!(v(&format!("1.4.0.post{max}")).bump_post().is_err());
+        assert!(v(&format!("1.4.0.dev{max}")).bump_dev().is_err());
+    }
+
+    #[test]
+    fn test_bump_pre_moves_forward() {
+        let cases = [
+            ("1.4.0", "1.4.1rc1"),
+            ("2", "3rc1"),
+            ("1.4.0.post1", "1.4.1rc1"),
+            ("1.4.0.post1.dev1", "1.4.1rc1"),
+            ("1.4.0.dev1", "1.4.0rc1"),
+            ("1.4.0b2", "1.4.0rc1"),
+            ("1.4.0rc1", "1.4.0rc2"),
+            ("1.4.0rc1.post1", "1.4.0rc2"),
+        ];
+        for (before, expect) in cases {
+            let bumped = v(before).bump_pre(PreKind::Rc, false).unwrap();
+            assert_eq!(bumped.to_string(), expect);
+            assert!(bumped > v(before), "{expect} is not greater than {before}");
+        }
+    }
+
+    #[test]
+    fn test_bump_dev_moves_forward() {
+        let cases = [
+            ("1.4.0", "1.4.1.dev1"),
+            ("2", "3.dev1"),
+            ("1.4.0.post1", "1.4.0.post2.dev1"),
+            ("1.4.0rc1", "1.4.0rc2.dev1"),
+            ("1.4.0.dev1", "1.4.0.dev2"),
+            ("1.4.0.post1.dev1", "1.4.0.post1.dev2"),
+        ];
+        for (before, expect) in cases {
+            let bumped = v(before).bump_dev().unwrap();
+            assert_eq!(bumped.to_string(), expect);
+            assert!(bumped > v(before), "{expect} is not greater than {before}");
+        }
+    }
+}
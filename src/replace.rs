@@ -0,0 +1,365 @@
+//! Search and replace the version in files
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::config::{Config, FileConfig};
+use crate::file_operations::FileChange;
+use crate::{Placeholders, ReleaseError};
+
+#[derive(Debug, Error)]
+pub enum ReplaceError {
+    #[error("File {} not found: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Invalid search pattern for {path:?}: {source}")]
+    Pattern { path: PathBuf, source: regex::Error },
+    #[error("Unable to find current version ({search:?}) in {path:?}")]
+    NotFound { path: PathBuf, search: String },
+}
+
+/// Compute the new contents of all configured files.
+///
+/// Every file is checked before returning, so the error lists all files that could not be bumped.
+pub fn prepare_file_changes(
+    config: &Config,
+    placeholders: &Placeholders,
+) -> Result<Vec<FileChange>, ReleaseError> {
+    let mut changes = Vec::new();
+    let mut failures = Vec::new();
+
+    for (file_path, file_config) in config.files.iter() {
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(source) => {
+                failures.push(ReplaceError::Read {
+                    path: file_path.clone(),
+                    source,
+                });
+                continue;
+            }
+        };
+
+        match replace_and_count(content.clone(), file_path, file_config, placeholders) {
+            Ok((result, replacements)) => changes.push(FileChange {
+                path: file_path.clone(),
+                original: Some(content),
+                contents: result,
+                replacements,
+            }),
+            Err(err) => failures.push(err),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(changes)
+    } else {
+        Err(ReleaseError::Files(failures))
+    }
+}
+
+/// Replace the search pattern rendered with the placeholders, fails when it is not found
+pub fn replace_version(
+    content: String,
+    file_path: &Path,
+    file_config: &FileConfig,
+    placeholders: &Placeholders,
+) -> Result<String, ReplaceError> {
+    replace_and_count(content, file_path, file_config, placeholders).map(|(result, _)| result)
+}
+
+/// Replace the version like [`replace_version`], also returns the number of replacements
+fn replace_and_count(
+    content: String,
+    file_path: &Path,
+    file_config: &FileConfig,
+    placeholders: &Placeholders,
+) -> Result<(String, usize), ReplaceError> {
+    let search_re = search_regex(
+        file_path,
+        &placeholders
+            .replace(&file_config.search)
+            // Escape + sign
+            .replace('+', "\\+"),
+    )?;
+
+    let replace_value = placeholders.replace(&file_config.replace);
+
+    let count = search_re.find_iter(&content).count();
+    if count > 0 {
+        Ok((
+            search_re.replace_all(&content, replace_value).to_string(),
+            count,
+        ))
+    } else {
+        Err(ReplaceError::NotFound {
+            path: file_path.to_path_buf(),
+            search: search_re.to_string(),
+        })
+    }
+}
+
+fn search_regex(file_path: &Path, pattern: &str) -> Result<Regex, ReplaceError> {
+    RegexBuilder::new(pattern)
+        .multi_line(true)
+        .build()
+        .map_err(|source| ReplaceError::Pattern {
+            path: file_path.to_path_buf(),
+            source,
+        })
+}
+
+/// Result of checking a single file for the current version
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCheck<'a> {
+    pub path: &'a Path,
+    pub ok: bool,
+    /// Versions found instead of the current version
+    pub found: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl<'a> Display for FileCheck<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.display();
+        match (&self.error, self.ok) {
+            (Some(error), _) => write!(f, "{path}: {error}"),
+            (None, true) => write!(f, "{path}: ok"),
+            (None, false) if self.found.is_empty() => write!(f, "{path}: version not found"),
+            (None, false) => write!(f, "{path}: found {}", self.found.join(", ")),
+        }
+    }
+}
+
+/// Check if the file contains the search pattern rendered with the current version.
+///
+/// When it doesn't, the search pattern is matched with any version to report what the file contains instead.
+pub fn check_file<'a>(
+    file_path: &'a Path,
+    file_config: &FileConfig,
+    current_version: &dyn Display,
+) -> FileCheck<'a> {
+    let mut check = FileCheck {
+        path: file_path,
+        ok: false,
+        found: vec![],
+        error: None,
+    };
+
+    let placeholders = Placeholders {
+        current_version,
+        new_version: current_version,
+    };
+    let content = match fs::read_to_string(file_path) {
+        Ok(content) => content,
+        Err(err) => {
+            check.error = Some(format!("File not found: {err}"));
+            return check;
+        }
+    };
+    check.ok = replace_version(content.clone(), file_path, file_config, &placeholders).is_ok();
+    if !check.ok {
+        match find_versions(&content, file_path, file_config) {
+            Ok(found) => check.found = found,
+            Err(err) => check.error = Some(err.to_string()),
+        }
+    }
+
+    check
+}
+
+/// Any version like string, e.g. `1.2.3`, `1.0.0-beta.1+build.5`, `2024.05.1` or `1.4.0rc2`
+const ANY_VERSION_PATTERN: &str = "[0-9]+(?:[-+.]?[0-9A-Za-z]+)*";
+
+/// Find all versions at the position of `{current_version}` in the search pattern
+fn find_versions(
+    content: &str,
+    file_path: &Path,
+    file_config: &FileConfig,
+) -> Result<Vec<String>, ReplaceError> {
+    let pattern = file_config
+        .search
+        .split("{current_version}")
+        // Escape + sign
+        .map(|part| part.replace('+', "\\+"))
+        .enumerate()
+        .fold(String::new(), |mut pattern, (i, part)| {
+            match i {
+                0 => {}
+                1 => pattern.push_str(&format!("(?P<version>{ANY_VERSION_PATTERN})")),
+                _ => pattern.push_str(&format!("(?:{ANY_VERSION_PATTERN})")),
+            }
+            pattern.push_str(&part);
+            pattern
+        });
+    let search_re = search_regex(file_path, &pattern)?;
+
+    let mut found: Vec<String> = vec![];
+    for captures in search_re.captures_iter(content) {
+        if let Some(version) = captures.name("version") {
+            if !found.iter().any(|v| v == version.as_str()) {
+                found.push(version.as_str().to_string());
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use semver::Version;
+    use tempfile::tempdir;
+
+    use crate::config::{Config, FileConfig};
+    use crate::replace::{check_file, prepare_file_changes, replace_version};
+    use crate::Placeholders;
+
+    #[test]
+    fn test_replace() {
+        let placeholders = Placeholders {
+            current_version: &Version::new(0, 1, 0),
+            new_version: &Version::new(0, 2, 0),
+        };
+
+        let content = r#"
+            version = "0.1.0"
+
+            [dependencies]
+            some-dep = { version = "0.1.0" }
+        "#;
+
+        let file_config = FileConfig {
+            search: "version = \"{current_version}\"$".to_string(),
+            replace: "version = \"{new_version}\"".to_string(),
+        };
+
+        let file_path = Path::new("Cargo.toml");
+
+        let res =
+            replace_version(content.to_string(), file_path, &file_config, &placeholders).unwrap();
+
+        assert_eq!(
+            res,
+            r#"
+            version = "0.2.0"
+
+            [dependencies]
+            some-dep = { version = "0.1.0" }
+        "#
+        );
+    }
+
+    #[test]
+    fn test_failure() {
+        let placeholders = Placeholders {
+            current_version: &Version::new(0, 1, 0),
+            new_version: &Version::new(0, 2, 0),
+        };
+
+        let content = r#"
+            version = "0.2.0"
+
+            [dependencies]
+            some-dep = { version = "0.1.0" }
+        "#;
+
+        let file_config = FileConfig {
+            search: "version = \"{current_version}\"$".to_string(),
+            replace: "version = \"{new_version}\"".to_string(),
+        };
+
+        let file_path = Path::new("Cargo.toml");
+
+        let res = replace_version(content.to_string(), file_path, &file_config, &placeholders);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_prepare_file_changes_reports_all_failures() {
+        let dir = tempdir().unwrap();
+        let placeholders = Placeholders {
+            current_version: &Version::new(0, 1, 0),
+            new_version: &Version::new(0, 2, 0),
+        };
+
+        let mut config = Config::default();
+        for (name, content) in [("VERSION", "0.1.0"), ("A", "0.3.0"), ("B", "0.4.0")] {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            config.files.insert(path, FileConfig::default());
+        }
+        config
+            .files
+            .insert(dir.path().join("MISSING"), FileConfig::default());
+
+        let err = prepare_file_changes(&config, &placeholders)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unable to bump 3 file(s)"));
+        for name in ["A", "B", "MISSING"] {
+            assert!(err.contains(&dir.path().join(name).display().to_string()));
+        }
+
+        // Nothing has been written
+        assert_eq!(
+            fs::read_to_string(dir.path().join("VERSION")).unwrap(),
+            "0.1.0"
+        );
+
+        config.files.shift_remove(&dir.path().join("A"));
+        config.files.shift_remove(&dir.path().join("B"));
+        config.files.shift_remove(&dir.path().join("MISSING"));
+        let changes = prepare_file_changes(&config, &placeholders).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].contents, "0.2.0");
+        assert_eq!(changes[0].original.as_deref(), Some("0.1.0"));
+        assert_eq!(changes[0].replacements, 1);
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(
+            &path,
+            r#"
+            version = "0.1.0-beta.1+build"
+
+            [dependencies]
+            some-dep = { version = "0.1.0" }
+        "#,
+        )
+        .unwrap();
+        let file_config = FileConfig {
+            search: "version = \"{current_version}\"$".to_string(),
+            replace: "version = \"{new_version}\"".to_string(),
+        };
+
+        let check = check_file(
+            &path,
+            &file_config,
+            &Version::parse("0.1.0-beta.1+build").unwrap(),
+        );
+        assert!(check.ok);
+        assert!(check.found.is_empty());
+
+        let check = check_file(&path, &file_config, &Version::new(0, 2, 0));
+        assert!(!check.ok);
+        assert_eq!(check.found, vec!["0.1.0-beta.1+build".to_string()]);
+        assert!(check.error.is_none());
+
+        let missing = dir.path().join("VERSION");
+        let check = check_file(&missing, &file_config, &Version::new(0, 2, 0));
+        assert!(!check.ok);
+        assert!(check.error.is_some());
+    }
+}
@@ -0,0 +1,331 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::calver::{CalVer, CalVerFormat};
+use crate::custom::{CustomFormat, CustomVersion, PartConfig};
+use crate::pep440::{Pep440Version, PreKind};
+use crate::{bump_semver, Error, Part, Result};
+
+/// Versioning scheme used to parse and bump versions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Scheme {
+    /// Semantic versioning, e.g. `1.2.3-beta.1`
+    #[default]
+    Semver,
+    /// Calendar versioning, e.g. `2024.05.3` for the format `YYYY.0M.MICRO`
+    Calver { format: String },
+    /// Python package versions, e.g. `1.4.0rc2`, `1.4.0.post1` or `1.4.0.dev3`
+    Pep440,
+    /// Custom scheme, the version is parsed into parts with the named groups of the `parse` regex and
+    /// written with the first fitting `serialize` template, e.g. `{major}.{minor}.{patch}.{build}`
+    Custom {
+        parse: String,
+        serialize: Vec<String>,
+        #[serde(default)]
+        parts: BTreeMap<String, PartConfig>,
+    },
+}
+
+/// A version of any of the schemes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnyVersion {
+    Semver(semver::Version),
+    Calver(CalVer),
+    Pep440(Pep440Version),
+    Custom(CustomVersion),
+}
+
+/// Prerelease channels in order, used when no channels are configured
+pub const DEFAULT_PRERELEASE_CHANNELS: [&str; 3] = ["alpha", "beta", "rc"];
+
+/// Options used when bumping a version
+#[derive(Debug, Clone)]
+pub struct BumpOptions {
+    /// Build metadata for the new version (SemVer only)
+    pub build: Option<String>,
+    /// The date calendar versions are rolled to
+    pub today: NaiveDate,
+    /// Ordered prerelease labels (SemVer only), e.g. `alpha`, `beta`, `rc`
+    pub prerelease_channels: Vec<String>,
+    /// Allow going back to an earlier prerelease channel
+    pub force: bool,
+}
+
+impl Default for BumpOptions {
+    fn default() -> Self {
+        BumpOptions {
+            build: None,
+            today: Local::now().date_naive(),
+            prerelease_channels: DEFAULT_PRERELEASE_CHANNELS.map(String::from).to_vec(),
+            force: false,
+        }
+    }
+}
+
+impl Scheme {
+    /// Parse a version according to the scheme
+    pub fn parse(&self, version: &str) -> Result<AnyVersion> {
+        match self {
+            Scheme::Semver => Ok(AnyVersion::Semver(
+                semver::Version::parse(version).map_err(|e| Error::InvalidVersion {
+                    version: version.to_string(),
+                    reason: e.to_string(),
+                })?,
+            )),
+            Scheme::Calver { format } => Ok(AnyVersion::Calver(
+                format.parse::<CalVerFormat>()?.parse(version)?,
+            )),
+            Scheme::Pep440 => Ok(AnyVersion::Pep440(version.parse()?)),
+            Scheme::Custom {
+                parse,
+                serialize,
+                parts,
+            } => Ok(AnyVersion::Custom(
+                CustomFormat::new(parse, serialize, parts)?.parse(version)?,
+            )),
+        }
+    }
+
+    /// Bump a part of a version.
+    ///
+    /// Calendar versions only support [`Part::Patch`] (or `micro`), which rolls the date to
+    /// [`BumpOptions::today`] and increments or resets the micro counter.
+    /// PEP 440 versions support the release segments, pre-releases `a`, `b` and `rc` (always in that order), removing the
+    /// pre-release with `release` and the parts `post` and `dev`. Build metadata becomes the local version.
+    /// Compound bumps like [`Part::Preminor`] start with an alpha pre-release when no label is given.
+    /// Custom versions support the named parts of the scheme, [`Part::Major`], [`Part::Minor`]
+    /// and [`Part::Patch`] bump the parts named `major`, `minor` and `patch`.
+    pub fn bump(
+        &self,
+        version: &AnyVersion,
+        part: &Part,
+        options: &BumpOptions,
+    ) -> Result<AnyVersion> {
+        match (version, part) {
+            (AnyVersion::Semver(v), _) => Ok(AnyVersion::Semver(bump_semver(v, part, options)?)),
+            (AnyVersion::Calver(v), Part::Patch) => Ok(AnyVersion::Calver(v.bump(options.today)?)),
+            (AnyVersion::Calver(v), Part::Named(name)) if name == "micro" => {
+                Ok(AnyVersion::Calver(v.bump(options.today)?))
+            }
+            (AnyVersion::Calver(v), _) => Err(Error::unsupported_part(
+                part,
+                v,
+                "CalVer versions only support 'patch'",
+            )),
+            (AnyVersion::Pep440(v), _) => {
+                let mut new_version = match part {
+                    Part::Major => v.bump_release(0)?,
+                    Part::Minor => v.bump_release(1)?,
+                    Part::Patch => v.bump_release(2)?,
+                    Part::Prerelease(Some(label)) => v.bump_pre(label.parse()?, options.force)?,
+                    Part::NextPrerelease => v.next_pre()?,
+                    Part::Premajor(label) => v.bump_release(0)?.with_pre(pre_kind(label)?, 1),
+                    Part::Preminor(label) => v.bump_release(1)?.with_pre(pre_kind(label)?, 1),
+                    Part::Prepatch(label) => v.bump_release(2)?.with_pre(pre_kind(label)?, 1),
+                    Part::Prerelease(None) => v.finalize()?,
+                    Part::Named(name) if name == "post" => v.bump_post()?,
+                    Part::Named(name) if name == "dev" => v.bump_dev()?,
+                    _ => {
+                        return Err(Error::unsupported_part(
+                            part,
+                            v,
+                            "PEP 440 versions don't have this part",
+                        ))
+                    }
+                };
+                if let Some(build) = &options.build {
+                    new_version = format!("{new_version}+{build}").parse().map_err(|_| {
+                        Error::InvalidBuildMetadata {
+                            build: build.clone(),
+                            reason: "not a valid PEP 440 local version".to_string(),
+                        }
+                    })?;
+                }
+                Ok(AnyVersion::Pep440(new_version))
+            }
+            (AnyVersion::Custom(v), Part::Major) => Ok(AnyVersion::Custom(v.bump("major")?)),
+            (AnyVersion::Custom(v), Part::Minor) => Ok(AnyVersion::Custom(v.bump("minor")?)),
+            (AnyVersion::Custom(v), Part::Patch) => Ok(AnyVersion::Custom(v.bump("patch")?)),
+            (AnyVersion::Custom(v), Part::Named(name)) => Ok(AnyVersion::Custom(v.bump(name)?)),
+            (AnyVersion::Custom(v), _) => Err(Error::unsupported_part(
+                part,
+                v,
+                "custom versions only support the names of their parts",
+            )),
+        }
+    }
+}
+
+/// PEP 440 pre-release kind of a label, alpha when there is none
+fn pre_kind(label: &Option<String>) -> Result<PreKind> {
+    label.as_deref().map_or(Ok(PreKind::Alpha), str::parse)
+}
+
+impl AnyVersion {
+    /// Name and value of the parts of the version, e.g. `major`, `minor` and `patch`.
+    ///
+    /// Parts that a scheme always has are included even when empty, so the names are the same for all
+    /// versions of a scheme.
+    pub fn parts(&self) -> Vec<(String, String)> {
+        let part = |name: &str, value: String| (name.to_string(), value);
+        match self {
+            AnyVersion::Semver(v) => vec![
+                part("major", v.major.to_string()),
+                part("minor", v.minor.to_string()),
+                part("patch", v.patch.to_string()),
+                part("prerelease", v.pre.to_string()),
+                part("build", v.build.to_string()),
+            ],
+            AnyVersion::Calver(v) => v
+                .parts()
+                .into_iter()
+                .map(|(name, value)| part(name, value))
+                .collect(),
+            AnyVersion::Pep440(v) => {
+                let release = |i: usize| v.release.get(i).copied().unwrap_or(0).to_string();
+                let optional = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+                vec![
+                    part("epoch", v.epoch.to_string()),
+                    part("major", release(0)),
+                    part("minor", release(1)),
+                    part("patch", release(2)),
+                    part(
+                        "pre",
+                        v.pre
+                            .map(|(kind, n)| format!("{kind}{n}"))
+                            .unwrap_or_default(),
+                    ),
+                    part("post", optional(v.post)),
+                    part("dev", optional(v.dev)),
+                    part("local", v.local.clone().unwrap_or_default()),
+                ]
+            }
+            AnyVersion::Custom(v) => v
+                .parts()
+                .map(|(name, value)| part(name, value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// Versions of the same scheme are ordered, custom versions only within the same format
+impl PartialOrd for AnyVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (AnyVersion::Semver(a), AnyVersion::Semver(b)) => a.partial_cmp(b),
+            (AnyVersion::Calver(a), AnyVersion::Calver(b)) => a.partial_cmp(b),
+            (AnyVersion::Pep440(a), AnyVersion::Pep440(b)) => a.partial_cmp(b),
+            (AnyVersion::Custom(a), AnyVersion::Custom(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AnyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyVersion::Semver(v) => v.fmt(f),
+            AnyVersion::Calver(v) => v.fmt(f),
+            AnyVersion::Pep440(v) => v.fmt(f),
+            AnyVersion::Custom(v) => v.fmt(f),
+        }
+    }
+}
+
+impl Serialize for AnyVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemes() {
+        let options = BumpOptions {
+            today: NaiveDate::from_ymd_opt(2024, 6, 12).unwrap(),
+            ..Default::default()
+        };
+        let cases = [
+            (Scheme::Semver, "1.2.3", Part::Minor, "1.3.0"),
+            (
+                Scheme::Calver {
+                    format: "YYYY.0M.MICRO".to_string(),
+                },
+                "2024.06.3",
+                Part::Patch,
+                "2024.06.4",
+            ),
+            (Scheme::Pep440, "1.4.0RC2", Part::Minor, "1.5.0"),
+            (
+                Scheme::Pep440,
+                "1.4.0rc2",
+                Part::Named("dev".to_string()),
+                "1.4.0rc3.dev1",
+            ),
+            (Scheme::Pep440, "1.4.0-rc2", Part::Prerelease(None), "1.4.0"),
+            (
+                Scheme::Pep440,
+                "1.4.2",
+                Part::Preminor(Some("b".to_string())),
+                "1.5.0b1",
+            ),
+            (
+                Scheme::Custom {
+                    parse: r"(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)\.(?P<build>\d+)"
+                        .to_string(),
+                    serialize: vec!["{major}.{minor}.{patch}.{build}".to_string()],
+                    parts: BTreeMap::new(),
+                },
+                "4.2.1.1037",
+                Part::Named("build".to_string()),
+                "4.2.1.1038",
+            ),
+        ];
+        for (scheme, before, part, expect) in cases {
+            let version = scheme.parse(before).unwrap();
+            let new_version = scheme.bump(&version, &part, &options).unwrap();
+            assert_eq!(new_version.to_string(), expect);
+        }
+    }
+
+    #[test]
+    fn test_parts() {
+        let names = |version: &AnyVersion| -> Vec<String> {
+            version.parts().into_iter().map(|(name, _)| name).collect()
+        };
+
+        let version = Scheme::Semver.parse("1.2.3-rc.1").unwrap();
+        assert_eq!(
+            version.parts()[3],
+            ("prerelease".to_string(), "rc.1".to_string())
+        );
+        assert_eq!(
+            names(&version),
+            ["major", "minor", "patch", "prerelease", "build"]
+        );
+
+        let version = Scheme::Pep440.parse("1.4rc2").unwrap();
+        assert_eq!(version.parts()[3], ("patch".to_string(), "0".to_string()));
+        assert_eq!(version.parts()[4], ("pre".to_string(), "rc2".to_string()));
+    }
+
+    #[test]
+    fn test_calver_unsupported_part() {
+        let scheme = Scheme::Calver {
+            format: "YYYY.0M.MICRO".to_string(),
+        };
+        let version = scheme.parse("2024.06.3").unwrap();
+        assert!(matches!(
+            scheme.bump(&version, &Part::Major, &BumpOptions::default()),
+            Err(Error::UnsupportedPart { .. })
+        ));
+    }
+}